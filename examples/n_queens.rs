@@ -0,0 +1,65 @@
+// Place N queens on an N x N chessboard so that no two queens attack each other.
+//
+// Usage: cargo run --release --example n_queens [n]
+
+use std::env;
+
+use sudoku::exact_cover::ExactCover;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Constraint {
+    Rank(usize),
+    File(usize),
+    Diagonal(usize),
+    AntiDiagonal(usize)
+}
+
+// a queen, given by its rank and file
+type Placement = (usize, usize);
+
+fn build_problem(n: usize) -> ExactCover<Placement, Constraint> {
+    let mut problem = ExactCover::new();
+
+    // every rank and file has a queen, but not every diagonal does
+    for diagonal in 0..2 * n - 1 {
+        problem.add_secondary_column(Constraint::Diagonal(diagonal)).expect("diagonals have no bounds");
+        problem.add_secondary_column(Constraint::AntiDiagonal(diagonal)).expect("diagonals have no bounds");
+    }
+
+    for rank in 0..n {
+        for file in 0..n {
+            problem.add_row((rank, file),
+                            vec![Constraint::Rank(rank),
+                                 Constraint::File(file),
+                                 Constraint::Diagonal(rank + file),
+                                 Constraint::AntiDiagonal(rank + n - 1 - file)]);
+        }
+    }
+    problem
+}
+
+fn board_string(n: usize, solution: &[Placement]) -> String {
+    let mut board = vec![vec!['.'; n]; n];
+    for (rank, file) in solution {
+        board[*rank][*file] = 'Q';
+    }
+    board.iter()
+         .map(|rank| rank.iter().collect::<String>())
+         .collect::<Vec<_>>()
+         .join("\n")
+}
+
+fn main() {
+    let n = match env::args().nth(1) {
+        Some(arg) => arg.parse().ok().filter(|n| *n > 0).expect("n must be a positive number"),
+        None => 8
+    };
+
+    let problem = build_problem(n);
+    let solution = problem.solutions().next();
+    match solution {
+        Some(solution) => println!("{}", board_string(n, &solution)),
+        None => println!("There is no solution for n = {}.", n)
+    }
+    println!("\n{} solutions", problem.count_solutions(None));
+}
@@ -0,0 +1,110 @@
+// Tile a rectangle with the twelve pentominoes, using each of them once.
+//
+// Usage: cargo run --release --example pentomino [height width]
+// The rectangle may be 3x20, 4x15, 5x12 or 6x10 (the default).
+
+use std::collections::BTreeSet;
+use std::env;
+
+use sudoku::exact_cover::ExactCover;
+
+const PIECES: [(char, [(i32, i32); 5]); 12] = [
+    ('F', [(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)]),
+    ('I', [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)]),
+    ('L', [(0, 0), (1, 0), (2, 0), (3, 0), (3, 1)]),
+    ('N', [(0, 1), (1, 1), (2, 0), (2, 1), (3, 0)]),
+    ('P', [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)]),
+    ('T', [(0, 0), (0, 1), (0, 2), (1, 1), (2, 1)]),
+    ('U', [(0, 0), (0, 2), (1, 0), (1, 1), (1, 2)]),
+    ('V', [(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)]),
+    ('W', [(0, 0), (1, 0), (1, 1), (2, 1), (2, 2)]),
+    ('X', [(0, 1), (1, 0), (1, 1), (1, 2), (2, 1)]),
+    ('Y', [(0, 1), (1, 0), (1, 1), (2, 1), (3, 1)]),
+    ('Z', [(0, 0), (0, 1), (1, 1), (2, 1), (2, 2)])
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Constraint {
+    Piece(char),
+    Cell(usize, usize)
+}
+
+// a piece placed on the board, as the cells it covers
+#[derive(Clone, Debug)]
+struct Placement {
+    piece: char,
+    cells: Vec<(usize, usize)>
+}
+
+// the distinct rotations and reflections of a piece, each shifted so that its
+// smallest row and column are zero
+fn orientations(cells: &[(i32, i32)]) -> BTreeSet<Vec<(i32, i32)>> {
+    let mut orientations = BTreeSet::new();
+    let mut cells = cells.to_vec();
+    for _ in 0..2 {
+        for _ in 0..4 {
+            cells = cells.iter().map(|(row, col)| (*col, -row)).collect();
+            let min_row = cells.iter().map(|(row, _)| *row).min().unwrap();
+            let min_col = cells.iter().map(|(_, col)| *col).min().unwrap();
+            let mut normalised: Vec<_> = cells.iter().map(|(row, col)| (row - min_row, col - min_col)).collect();
+            normalised.sort();
+            orientations.insert(normalised);
+        }
+        cells = cells.iter().map(|(row, col)| (*row, -col)).collect();
+    }
+    orientations
+}
+
+fn build_problem(height: usize, width: usize) -> ExactCover<Placement, Constraint> {
+    let mut problem = ExactCover::new();
+    for (piece, cells) in PIECES.iter() {
+        for orientation in orientations(cells) {
+            for top in 0..height as i32 {
+                for left in 0..width as i32 {
+                    let cells: Vec<_> = orientation.iter().map(|(row, col)| (row + top, col + left)).collect();
+                    if cells.iter().all(|(row, col)| *row < height as i32 && *col < width as i32) {
+                        let cells: Vec<_> = cells.iter().map(|(row, col)| (*row as usize, *col as usize)).collect();
+                        let mut constraints = vec![Constraint::Piece(*piece)];
+                        constraints.extend(cells.iter().map(|(row, col)| Constraint::Cell(*row, *col)));
+                        problem.add_row(Placement{piece: *piece, cells}, constraints);
+                    }
+                }
+            }
+        }
+    }
+    problem
+}
+
+fn board_string(height: usize, width: usize, solution: &[Placement]) -> String {
+    let mut board = vec![vec!['.'; width]; height];
+    for placement in solution {
+        for (row, col) in &placement.cells {
+            board[*row][*col] = placement.piece;
+        }
+    }
+    board.iter()
+         .map(|row| row.iter().collect::<String>())
+         .collect::<Vec<_>>()
+         .join("\n")
+}
+
+fn main() {
+    let args: Vec<usize> = env::args().skip(1)
+                                      .map(|arg| arg.parse().expect("dimensions must be numbers"))
+                                      .collect();
+    let (height, width) = match args.as_slice() {
+        [height, width] if height * width == 60 => (*height, *width),
+        [] => (6, 10),
+        _ => {
+            println!("The rectangle must have an area of 60 cells.");
+            return;
+        }
+    };
+
+    let problem = build_problem(height, width);
+    let solution = problem.solutions().next();
+    match solution {
+        Some(solution) => println!("{}", board_string(height, width, &solution)),
+        None => println!("There is no tiling of a {}x{} rectangle.", height, width)
+    }
+}
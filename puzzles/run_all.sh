@@ -19,6 +19,11 @@ function run_all_in_directory {
 }
 
 run_all_in_directory "./puzzles/4x4"
+run_all_in_directory "./puzzles/6x6"
+run_all_in_directory "./puzzles/8x8"
 run_all_in_directory "./puzzles/9x9"
+run_all_in_directory "./puzzles/12x12"
 run_all_in_directory "./puzzles/16x16"
 run_all_in_directory "./puzzles/25x25"
+run_all_in_directory "./puzzles/jigsaw"
+run_all_in_directory "./puzzles/sudoku-x"
@@ -0,0 +1,109 @@
+use super::solver;
+use super::solver::Uniqueness;
+use super::Sudoku;
+
+// Find the clues of a Sudoku that are redundant, i.e., that can each be removed
+// without the solution becoming ambiguous, as (row, col, val). The clues are
+// redundant individually: removing several of them together may not leave a
+// unique solution. A Sudoku without a unique solution has no redundant clues.
+pub fn redundant_clues(sudoku: &Sudoku) -> Vec<(usize, usize, usize)> {
+    if !has_unique_solution(sudoku) {
+        return Vec::new();
+    }
+
+    let mut puzzle = sudoku.clone();
+    let mut redundant = Vec::new();
+    for (row, col, val) in sudoku.completed_cells() {
+        puzzle.clear_cell(row, col);
+        if has_unique_solution(&puzzle) {
+            redundant.push((row, col, val));
+        }
+        puzzle.set_cell_value(row, col, val);
+    }
+    redundant.sort_unstable();
+    redundant
+}
+
+// check whether a Sudoku is minimal, i.e., it has a unique solution, but would
+// not if any one of its clues were removed
+pub fn is_minimal(sudoku: &Sudoku) -> bool {
+    has_unique_solution(sudoku) && redundant_clues(sudoku).is_empty()
+}
+
+fn has_unique_solution(sudoku: &Sudoku) -> bool {
+    sudoku.is_consistent() && solver::count_solutions(sudoku, Some(2)) == 1
+}
+
+// a summary of whether a Sudoku is minimal, and if not why not
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Analysis {
+    pub uniqueness: Uniqueness,
+    pub redundant_clues: Vec<(usize, usize, usize)>
+}
+
+impl Analysis {
+    pub fn is_minimal(&self) -> bool {
+        matches!(self.uniqueness, Uniqueness::Unique(_)) && self.redundant_clues.is_empty()
+    }
+}
+
+pub fn analyze(sudoku: &Sudoku) -> Analysis {
+    let uniqueness = if sudoku.is_consistent() {
+        solver::uniqueness(sudoku)
+    } else {
+        Uniqueness::None
+    };
+    let redundant_clues = match uniqueness {
+        Uniqueness::Unique(_) => redundant_clues(sudoku),
+        _ => Vec::new()
+    };
+    Analysis {
+        uniqueness,
+        redundant_clues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analyze, is_minimal, redundant_clues};
+    use super::super::solver::Uniqueness;
+    use super::super::Sudoku;
+
+    #[test]
+    fn test_redundant_clues() {
+        // each clue can be removed on its own, but not all of them together
+        let puzzle: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        assert_eq!(8, redundant_clues(&puzzle).len());
+        assert!(!is_minimal(&puzzle));
+
+        let minimal: Sudoku = "..3. .... .2.4 1...".parse().unwrap();
+        assert!(redundant_clues(&minimal).is_empty());
+        assert!(is_minimal(&minimal));
+
+        // puzzles without a unique solution are not minimal
+        let ambiguous: Sudoku = "3... .... .... ....".parse().unwrap();
+        assert!(redundant_clues(&ambiguous).is_empty());
+        assert!(!is_minimal(&ambiguous));
+        let unsolvable: Sudoku = "3.4. ..2. 1... .4.3".parse().unwrap();
+        assert!(!is_minimal(&unsolvable));
+    }
+
+    #[test]
+    fn test_analyze() {
+        // removing redundant clues one at a time, while there are any, leaves a
+        // minimal puzzle
+        let mut puzzle: Sudoku = "1~2~3~4 3~4~1~2 2~1~4~3 4~3~2~1".parse().unwrap();
+        let analysis = analyze(&puzzle);
+        assert_eq!(16, analysis.redundant_clues.len());
+        assert!(!analysis.is_minimal());
+        while let Some((row, col, _)) = redundant_clues(&puzzle).first().copied() {
+            puzzle.clear_cell(row, col);
+        }
+        assert!(is_minimal(&puzzle));
+        assert!(analyze(&puzzle).is_minimal());
+
+        let analysis = analyze(&"3... .... .... ....".parse().unwrap());
+        assert!(matches!(analysis.uniqueness, Uniqueness::Multiple(_, _)));
+        assert!(!analysis.is_minimal());
+    }
+}
@@ -0,0 +1,606 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::Sudoku;
+use super::SudokuError;
+
+// The values that remain possible for each cell of a Sudoku, i.e., its pencil
+// marks, as a bit set for each cell (bit val - 1 is set if val is possible),
+// which is why Sudokus can be no larger than 64x64. A cell whose value is known
+// has no candidates. Cells are numbered row by row, and the units, i.e., the
+// rows, columns and blocks, in that order, followed by the diagonals of a
+// Sudoku-X.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CandidateGrid {
+    // the values placed so far, which also gives the layout of the grid
+    sudoku: Sudoku,
+    pub(crate) dimension: usize,
+    pub(crate) values: Vec<Option<usize>>,
+    candidates: Vec<u64>,
+    pub(crate) units: Vec<Vec<usize>>,
+    pub(crate) cell_units: Vec<[usize; 3]>,
+    // the diagonal units of each cell, which only a Sudoku-X has
+    cell_diagonals: Vec<Vec<usize>>
+}
+
+impl CandidateGrid {
+    const H_SEP_CHAR: char = '-';
+    const V_SEP_CHAR: char = '|';
+    const ELIMINATED_CHAR: char = '.';
+    const SPACE_CHAR: char = ' ';
+
+    pub(crate) const ROW: usize = 0;
+    pub(crate) const COL: usize = 1;
+    pub(crate) const BLOCK: usize = 2;
+
+    // create the grid for a Sudoku, with every value that is not already in a
+    // cell's row, column or block as a candidate for the cell
+    pub fn new(sudoku: &Sudoku) -> Self {
+        let dimension = sudoku.dimension();
+        let all = if dimension == 64 { u64::MAX } else { (1 << dimension) - 1 };
+        let mut grid = Self {
+            sudoku: sudoku.clone(),
+            dimension,
+            values: vec![None; dimension * dimension],
+            candidates: vec![all; dimension * dimension],
+            units: vec![Vec::with_capacity(dimension); 3 * dimension + sudoku.num_diagonals()],
+            cell_units: Vec::with_capacity(dimension * dimension),
+            cell_diagonals: Vec::with_capacity(dimension * dimension)
+        };
+
+        for row in 0..dimension {
+            for col in 0..dimension {
+                let cell = grid.cell(row, col);
+                let cell_units = [row, dimension + col, 2 * dimension + sudoku.block_no(row, col)];
+                let cell_diagonals: Vec<usize> = sudoku.diagonal_nos(row, col)
+                                                       .into_iter()
+                                                       .map(|diagonal| 3 * dimension + diagonal)
+                                                       .collect();
+                for unit in cell_units.iter().chain(&cell_diagonals) {
+                    grid.units[*unit].push(cell);
+                }
+                grid.cell_units.push(cell_units);
+                grid.cell_diagonals.push(cell_diagonals);
+            }
+        }
+
+        for (row, col, val) in sudoku.completed_cells() {
+            grid.place(grid.cell(row, col), val);
+        }
+        grid
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    pub fn cell_value(&self, row: usize, col: usize) -> Option<usize> {
+        self.values[self.cell(row, col)]
+    }
+
+    // place a value in a cell, eliminating it from the rest of the cell's row,
+    // column and block
+    pub fn set_cell_value(&mut self, row: usize, col: usize, val: usize) {
+        debug_assert!(val > 0 && val <= self.dimension);
+        self.place(self.cell(row, col), val);
+    }
+
+    // the values still possible for a cell whose value is not yet known
+    pub fn cell_candidates(&self, row: usize, col: usize) -> Vec<usize> {
+        values(self.candidates[self.cell(row, col)]).collect()
+    }
+
+    // whether a value is a candidate for a cell (which it cannot be for a cell
+    // or value outside the grid)
+    pub fn is_candidate(&self, row: usize, col: usize, val: usize) -> bool {
+        self.in_range(row, col, val) && self.has_candidate(self.cell(row, col), val)
+    }
+
+    // remove a candidate from a cell, returning whether it was a candidate
+    pub fn eliminate_candidate(&mut self, row: usize, col: usize, val: usize) -> bool {
+        if !self.in_range(row, col, val) {
+            return false;
+        }
+        let cell = self.cell(row, col);
+        let was_candidate = self.has_candidate(cell, val);
+        self.eliminate(cell, val);
+        was_candidate
+    }
+
+    // make a value a candidate for a cell again, returning whether it was not a
+    // candidate already (cells whose value is known, and cells or values outside
+    // the grid, are left unchanged)
+    pub fn restore_candidate(&mut self, row: usize, col: usize, val: usize) -> bool {
+        if !self.in_range(row, col, val) {
+            return false;
+        }
+        let cell = self.cell(row, col);
+        if self.values[cell].is_some() || self.has_candidate(cell, val) {
+            return false;
+        }
+        self.candidates[cell] |= bit(val);
+        true
+    }
+
+    // Eliminate the value of each cell from the rest of its units, and place any
+    // value that is the last candidate for its cell, until no more values can be
+    // placed. Returns the number of values placed.
+    pub fn propagate(&mut self) -> usize {
+        for cell in 0..self.num_cells() {
+            if let Some(val) = self.values[cell] {
+                self.place(cell, val);
+            }
+        }
+
+        let mut placed = 0;
+        while let Some(cell) = (0..self.num_cells()).find(|cell| self.candidates[*cell].count_ones() == 1) {
+            let val = values(self.candidates[cell]).next().unwrap_or_default();
+            self.place(cell, val);
+            placed += 1;
+        }
+        placed
+    }
+
+    // the values eliminated from the cells whose values are not yet known, as
+    // (row, col, val)
+    pub fn eliminated_candidates(&self) -> Vec<(usize, usize, usize)> {
+        let mut eliminated = Vec::new();
+        for cell in (0..self.num_cells()).filter(|cell| self.values[*cell].is_none()) {
+            let (row, col) = self.position(cell);
+            eliminated.extend((1..=self.dimension).filter(|val| !self.has_candidate(cell, *val))
+                                                  .map(|val| (row, col, val)));
+        }
+        eliminated
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.values.iter().all(Option::is_some)
+    }
+
+    // the Sudoku with the values placed so far
+    pub fn to_sudoku(&self) -> Sudoku {
+        self.sudoku.clone()
+    }
+
+    // whether a cell and a value are within the grid
+    fn in_range(&self, row: usize, col: usize, val: usize) -> bool {
+        row < self.dimension && col < self.dimension && val > 0 && val <= self.dimension
+    }
+
+    pub(crate) fn num_cells(&self) -> usize {
+        self.values.len()
+    }
+
+    pub(crate) fn cell(&self, row: usize, col: usize) -> usize {
+        col + row * self.dimension
+    }
+
+    pub(crate) fn position(&self, cell: usize) -> (usize, usize) {
+        (cell / self.dimension, cell % self.dimension)
+    }
+
+    pub(crate) fn candidates(&self, cell: usize) -> u64 {
+        self.candidates[cell]
+    }
+
+    pub(crate) fn has_candidate(&self, cell: usize, val: usize) -> bool {
+        self.candidates[cell] & bit(val) != 0
+    }
+
+    // the cells of a unit with a value as a candidate
+    pub(crate) fn cells_with(&self, unit: usize, val: usize) -> Vec<usize> {
+        self.units[unit].iter().copied().filter(|cell| self.has_candidate(*cell, val)).collect()
+    }
+
+    // check whether two different cells share a unit
+    pub(crate) fn sees(&self, cell: usize, other: usize) -> bool {
+        cell != other && ((0..3).any(|kind| self.cell_units[cell][kind] == self.cell_units[other][kind]) ||
+                          self.cell_diagonals[cell].iter().any(|unit| self.cell_diagonals[other].contains(unit)))
+    }
+
+    // place a value in a cell, eliminating it from the rest of the cell's units
+    pub(crate) fn place(&mut self, cell: usize, val: usize) {
+        self.set(cell, val);
+        for unit in self.cell_units[cell].iter().chain(&self.cell_diagonals[cell]) {
+            for other in &self.units[*unit] {
+                self.candidates[*other] &= !bit(val);
+            }
+        }
+    }
+
+    pub(crate) fn eliminate(&mut self, cell: usize, val: usize) {
+        self.candidates[cell] &= !bit(val);
+    }
+
+    // place a value in a cell, leaving the candidates of other cells as they are
+    fn set(&mut self, cell: usize, val: usize) {
+        let (row, col) = self.position(cell);
+        self.values[cell] = Some(val);
+        self.candidates[cell] = 0;
+        self.sudoku.set_cell_value(row, col, val);
+    }
+
+    // the number of characters taken by each value, and between the values, in
+    // a cell's mini-grid
+    fn slot_widths(dimension: usize) -> (usize, usize) {
+        let width = dimension.to_string().len();
+        (width, if width > 1 { 1 } else { 0 })
+    }
+
+    // the character offset of a column in each line of the grid, where each
+    // mini-grid is as wide as a block
+    fn col_offset(dimension: usize, block_width: usize, col: usize) -> usize {
+        let (width, gap) = CandidateGrid::slot_widths(dimension);
+        let cell_width = block_width * width + (block_width - 1) * gap;
+        2 + col * (cell_width + 1) + (col / block_width) * 2
+    }
+
+    // one line of a cell's mini-grid, which has the same shape as a block and a
+    // slot for each value, showing either the candidates of the cell or the value
+    // placed in it
+    fn mini_grid_line(&self, cell: usize, line: usize) -> String {
+        let block_width = self.sudoku.block_width();
+        let (width, gap) = CandidateGrid::slot_widths(self.dimension);
+        let slots: Vec<String> = (0..block_width).map(|slot| {
+            let val = line * block_width + slot + 1;
+            let slot_str = match self.values[cell] {
+                Some(value) if value == val => val.to_string(),
+                Some(_) => CandidateGrid::SPACE_CHAR.to_string(),
+                None if self.has_candidate(cell, val) => val.to_string(),
+                None => CandidateGrid::ELIMINATED_CHAR.to_string()
+            };
+            format!("{:>w$}", slot_str, w=width)
+        }).collect();
+        slots.join(&CandidateGrid::SPACE_CHAR.to_string().repeat(gap))
+    }
+
+    fn grid_string(&self) -> String {
+        let (block_height, block_width) = (self.sudoku.block_height(), self.sudoku.block_width());
+        let mut lines = Vec::new();
+        for row in 0..self.dimension {
+            for line in 0..block_height {
+                let mut line_str = CandidateGrid::V_SEP_CHAR.to_string();
+                for col in 0..self.dimension {
+                    if col > 0 && col % block_width == 0 {
+                        line_str.push(CandidateGrid::SPACE_CHAR);
+                        line_str.push(CandidateGrid::V_SEP_CHAR);
+                    }
+                    line_str.push(CandidateGrid::SPACE_CHAR);
+                    line_str.push_str(&self.mini_grid_line(self.cell(row, col), line));
+                }
+                line_str.push(CandidateGrid::SPACE_CHAR);
+                line_str.push(CandidateGrid::V_SEP_CHAR);
+                lines.push(line_str);
+            }
+            // separate the rows of cells with a blank line, and the rows of blocks
+            // with a horizontal rule
+            if row % block_height != block_height - 1 {
+                lines.push(String::new());
+            }
+        }
+
+        let horiz_rule = CandidateGrid::H_SEP_CHAR.to_string().repeat(lines[0].len());
+        let mut grid_str = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            if i % (block_height * (block_height + 1) - 1) == 0 {
+                grid_str.push_str(&horiz_rule);
+                grid_str.push('\n');
+            }
+            grid_str.push_str(line);
+            grid_str.push('\n');
+        }
+        grid_str.push_str(&horiz_rule);
+        grid_str
+    }
+}
+
+// Parses a grid in the layout produced by Display. Blank lines and horizontal
+// rules are skipped, and each of the remaining lines gives one line of the
+// mini-grids of a row of cells. In each mini-grid, a cell whose value is not known
+// shows each value as either a candidate or eliminated, whereas a cell whose value
+// is known shows only that value. As for a Sudoku, the layout of the regions of a
+// jigsaw Sudoku, and the variant, may follow the grid.
+impl FromStr for CandidateGrid {
+    type Err = SudokuError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let (str, variant) = Sudoku::split_variant(str)?;
+        let (grid_str, layout) = Sudoku::split_regions(&str);
+        let lines: Vec<(usize, Vec<char>)> = grid_str.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().chars().all(|c| c == CandidateGrid::H_SEP_CHAR))
+            .map(|(i, line)| (i + 1, line.chars().collect()))
+            .collect();
+        let malformed = |line: usize| SudokuError::MalformedCandidateGrid { line };
+        let end_line = grid_str.lines().count() + 1;
+
+        // Infer the shape of the blocks from the first line: there are as many
+        // blocks across it as there are rows in a block, and the width of a block
+        // depends on the number of cells across it.
+        let (first_line, first) = lines.first().ok_or_else(|| malformed(end_line))?;
+        let separators: Vec<usize> = first.iter()
+                                          .enumerate()
+                                          .filter(|(_, c)| **c == CandidateGrid::V_SEP_CHAR)
+                                          .map(|(i, _)| i)
+                                          .collect();
+        let block_height = separators.len().saturating_sub(1);
+        let block_chars = separators.get(1).map_or(0, |second| second - separators[0] - 1);
+        let block_width = (1..=Sudoku::MAX_DIMENSION).find(|block_width| {
+            let (width, gap) = CandidateGrid::slot_widths(block_height * block_width);
+            let cell_width = block_width * width + (block_width - 1) * gap;
+            block_width * (cell_width + 1) + 1 == block_chars
+        }).unwrap_or(0);
+        let mut sudoku = Sudoku::try_with_block_shape(block_height, block_width).map_err(|_| malformed(*first_line))?;
+        if let Some(layout) = layout {
+            // the candidates of a jigsaw Sudoku are laid out as they would be
+            // for a regular one of the same size
+            let jigsaw = Sudoku::try_with_regions(&Sudoku::parse_regions(layout, sudoku.dimension())?)?;
+            if (jigsaw.block_height(), jigsaw.block_width()) != (block_height, block_width) {
+                return Err(malformed(*first_line));
+            }
+            sudoku = jigsaw;
+        }
+        sudoku.set_variant(variant);
+        let dimension = sudoku.dimension();
+        let num_lines = dimension * block_height;
+        if lines.len() != num_lines {
+            return Err(malformed(lines.get(num_lines).map_or(end_line, |(line, _)| *line)));
+        }
+
+        // the values shown in each cell, and whether it has any blank or
+        // eliminated slots
+        let num_cells = dimension * dimension;
+        let mut shown = vec![0; num_cells];
+        let mut blank = vec![false; num_cells];
+        let mut eliminated = vec![false; num_cells];
+        let (width, gap) = CandidateGrid::slot_widths(dimension);
+        for (i, (line_no, chars)) in lines.iter().enumerate() {
+            let row = i / block_height;
+            let line = i % block_height;
+            for col in 0..dimension {
+                let cell = col + row * dimension;
+                let offset = CandidateGrid::col_offset(dimension, block_width, col);
+                for slot in 0..block_width {
+                    let start = offset + slot * (width + gap);
+                    let slot_str: String = chars.get(start..start + width)
+                                                .ok_or_else(|| malformed(*line_no))?
+                                                .iter()
+                                                .collect();
+                    let val = line * block_width + slot + 1;
+                    match slot_str.trim() {
+                        "" => blank[cell] = true,
+                        slot if slot == CandidateGrid::ELIMINATED_CHAR.to_string() => eliminated[cell] = true,
+                        slot if slot.parse() == Ok(val) => shown[cell] |= bit(val),
+                        _ => return Err(malformed(*line_no))
+                    }
+                }
+            }
+        }
+
+        let mut grid = CandidateGrid::new(&sudoku);
+        for cell in 0..num_cells {
+            if blank[cell] {
+                // a known value must be shown on its own
+                if eliminated[cell] || shown[cell].count_ones() != 1 {
+                    return Err(malformed(lines[(cell / dimension) * block_height].0));
+                }
+                grid.set(cell, values(shown[cell]).next().unwrap_or_default());
+            } else {
+                grid.candidates[cell] = shown[cell];
+            }
+        }
+        Ok(grid)
+    }
+}
+
+impl fmt::Display for CandidateGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.grid_string())?;
+        if let Some(regions_str) = self.sudoku.regions_string() {
+            write!(f, "\n{}", regions_str)?;
+        }
+        if let Some(variant_str) = self.sudoku.variant_string() {
+            write!(f, "\n{}", variant_str)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn bit(val: usize) -> u64 {
+    1 << (val - 1)
+}
+
+// the values in a set of candidates, in increasing order
+pub(crate) fn values(mut candidates: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if candidates == 0 {
+            None
+        } else {
+            let val = candidates.trailing_zeros() as usize + 1;
+            candidates &= candidates - 1;
+            Some(val)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CandidateGrid;
+    use super::super::{Sudoku, SudokuError, Variant};
+
+    #[test]
+    fn test_new() {
+        let sudoku: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        let grid = CandidateGrid::new(&sudoku);
+        assert_eq!(Some(3), grid.cell_value(0, 0));
+        assert!(grid.cell_candidates(0, 0).is_empty());
+        assert_eq!(vec![2], grid.cell_candidates(0, 1));
+        assert_eq!(vec![4], grid.cell_candidates(1, 0));
+        assert!(!grid.is_solved());
+        assert_eq!(sudoku, grid.to_sudoku());
+    }
+
+    #[test]
+    fn test_new_diagonal() {
+        let mut sudoku: Sudoku = "1... .... .... ....".parse().unwrap();
+        sudoku.set_variant(Variant::X);
+        let mut grid = CandidateGrid::new(&sudoku);
+        assert!(!grid.is_candidate(3, 3, 1));
+        assert!(grid.is_candidate(3, 2, 1));
+        grid.set_cell_value(1, 2, 2);
+        assert!(!grid.is_candidate(2, 1, 2));
+        assert!(!grid.is_candidate(3, 0, 2));
+        assert!(grid.is_candidate(2, 3, 2));
+    }
+
+    #[test]
+    fn test_eliminate_restore() {
+        let mut grid = CandidateGrid::new(&Sudoku::new(4));
+        assert!(grid.eliminate_candidate(0, 0, 2));
+        assert!(!grid.eliminate_candidate(0, 0, 2));
+        assert!(!grid.is_candidate(0, 0, 2));
+        assert_eq!(vec![1, 3, 4], grid.cell_candidates(0, 0));
+        assert_eq!(vec![(0, 0, 2)], grid.eliminated_candidates());
+        assert!(grid.restore_candidate(0, 0, 2));
+        assert!(!grid.restore_candidate(0, 0, 2));
+        assert_eq!(vec![1, 2, 3, 4], grid.cell_candidates(0, 0));
+
+        // placing a value removes it from the rest of the row, column and block
+        grid.set_cell_value(0, 0, 1);
+        assert!(!grid.restore_candidate(0, 0, 2));
+        assert!(!grid.is_candidate(0, 3, 1) && !grid.is_candidate(3, 0, 1) && !grid.is_candidate(1, 1, 1));
+        assert!(grid.is_candidate(1, 2, 1));
+
+        // cells and values outside the grid are never candidates, and are left alone
+        let before = grid.clone();
+        for (row, col, val) in [(1, 1, 0), (1, 1, 5), (1, 1, 65), (1, 1, usize::MAX), (4, 0, 2), (0, 4, 2)] {
+            assert!(!grid.is_candidate(row, col, val));
+            assert!(!grid.eliminate_candidate(row, col, val));
+            assert!(!grid.restore_candidate(row, col, val));
+        }
+        assert_eq!(before, grid);
+    }
+
+    #[test]
+    fn test_propagate() {
+        let sudoku: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        let mut grid = CandidateGrid::new(&sudoku);
+        assert_eq!(8, grid.propagate());
+        assert!(grid.is_solved());
+        assert!(grid.to_sudoku().is_consistent());
+
+        // restored candidates that conflict with a placed value are eliminated again
+        let mut grid = CandidateGrid::new(&sudoku);
+        grid.restore_candidate(0, 1, 3);
+        assert_eq!(vec![2, 3], grid.cell_candidates(0, 1));
+        grid.propagate();
+        assert_eq!(Some(2), grid.cell_value(0, 1));
+
+        let mut grid = CandidateGrid::new(&Sudoku::new(9));
+        assert_eq!(0, grid.propagate());
+    }
+
+    #[test]
+    fn test_display() {
+        let grid = CandidateGrid::new(&"1... .... .... ....".parse().unwrap());
+        let expected = "-----------------\n\
+                        | 1  .2 | .2 .2 |\n\
+                        |    34 | 34 34 |\n\
+                        \n\
+                        | .2 .2 | 12 12 |\n\
+                        | 34 34 | 34 34 |\n\
+                        -----------------\n\
+                        | .2 12 | 12 12 |\n\
+                        | 34 34 | 34 34 |\n\
+                        \n\
+                        | .2 12 | 12 12 |\n\
+                        | 34 34 | 34 34 |\n\
+                        -----------------";
+        assert_eq!(expected, grid.to_string());
+    }
+
+    #[test]
+    fn test_from_str() {
+        for sudoku in &["1... .... .... ....", "3.4. .1.2 .4.3 2.1."] {
+            let mut grid = CandidateGrid::new(&sudoku.parse().unwrap());
+            grid.eliminate_candidate(1, 3, 4);
+            assert_eq!(Ok(grid.clone()), grid.to_string().parse());
+        }
+
+        // the grids of larger Sudokus have wider slots
+        let mut grid = CandidateGrid::new(&Sudoku::new(16));
+        grid.set_cell_value(3, 5, 12);
+        grid.eliminate_candidate(15, 15, 16);
+        assert_eq!(Ok(grid.clone()), grid.to_string().parse());
+        let mut grid = CandidateGrid::new(&Sudoku::new(9));
+        grid.set_cell_value(8, 8, 9);
+        assert_eq!(Ok(grid.clone()), grid.to_string().parse());
+
+        // as are those of Sudokus with rectangular blocks, which have mini-grids
+        // of the same shape
+        for sudoku in &[Sudoku::new(6), Sudoku::with_block_shape(3, 2), Sudoku::new(12)] {
+            let mut grid = CandidateGrid::new(sudoku);
+            grid.set_cell_value(0, 1, 2);
+            grid.eliminate_candidate(4, 4, 5);
+            assert_eq!(Ok(grid.clone()), grid.to_string().parse());
+        }
+        let grid = CandidateGrid::new(&Sudoku::new(6));
+        assert_eq!(Some("| 123 123 123 | 123 123 123 |"), grid.to_string().lines().nth(1));
+
+        // the layout of the regions of a jigsaw Sudoku follows its grid
+        let sudoku: Sudoku = "1... .... .... ....\nregions: AAAB CABB CCDB CDDD".parse().unwrap();
+        let mut grid = CandidateGrid::new(&sudoku);
+        grid.eliminate_candidate(3, 3, 2);
+        assert!(!grid.is_candidate(1, 1, 1));
+        assert!(grid.is_candidate(1, 0, 2));
+        assert_eq!(Ok(grid.clone()), grid.to_string().parse());
+        assert!(grid.to_string().ends_with("regions:\nA A A B\nC A B B\nC C D B\nC D D D"));
+
+        // as does the variant, which gives a Sudoku-X its diagonal units
+        let mut sudoku: Sudoku = "1... .... .... ....".parse().unwrap();
+        sudoku.set_variant(Variant::X);
+        let grid = CandidateGrid::new(&sudoku);
+        assert!(grid.to_string().ends_with("\nvariant: x"));
+        let parsed: CandidateGrid = grid.to_string().parse().unwrap();
+        assert!(!parsed.is_candidate(3, 3, 1));
+        assert_eq!(grid, parsed);
+    }
+
+    #[test]
+    fn test_from_str_errors() {
+        let malformed = |line| Err(SudokuError::MalformedCandidateGrid { line });
+        assert_eq!(malformed(1), "".parse::<CandidateGrid>());
+        assert_eq!(malformed(1), "| 1".parse::<CandidateGrid>());
+        assert_eq!(malformed(3), "---\n| 1 | 2 | 3 |".parse::<CandidateGrid>());
+        // a line is missing
+        assert_eq!(malformed(13), "-----------------\n\
+                                   | 1  .2 | .2 .2 |\n\
+                                   |    34 | 34 34 |\n\
+                                   \n\
+                                   | .2 .2 | 12 12 |\n\
+                                   | 34 34 | 34 34 |\n\
+                                   -----------------\n\
+                                   | .2 12 | 12 12 |\n\
+                                   | 34 34 | 34 34 |\n\
+                                   \n\
+                                   | .2 12 | 12 12 |\n\
+                                   -----------------".parse::<CandidateGrid>());
+        // a value out of place, and a known value shown with candidates
+        assert_eq!(malformed(2), "| 1  .2 | .2 .2 |\n\
+                                  | 4  34 | 34 34 |\n\
+                                  | .2 .2 | 12 12 |\n\
+                                  | 34 34 | 34 34 |\n\
+                                  | .2 12 | 12 12 |\n\
+                                  | 34 34 | 34 34 |\n\
+                                  | .2 12 | 12 12 |\n\
+                                  | 34 34 | 34 34 |".parse::<CandidateGrid>());
+        assert_eq!(malformed(1), "| 1. .2 | .2 .2 |\n\
+                                  |    34 | 34 34 |\n\
+                                  | .2 .2 | 12 12 |\n\
+                                  | 34 34 | 34 34 |\n\
+                                  | .2 12 | 12 12 |\n\
+                                  | 34 34 | 34 34 |\n\
+                                  | .2 12 | 12 12 |\n\
+                                  | 34 34 | 34 34 |".parse::<CandidateGrid>());
+    }
+}
@@ -1,6 +1,19 @@
+use std::borrow::BorrowMut;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::rng::Rng;
+use super::stats::SearchStats;
+
+mod chooser;
+mod parallel;
+
+pub use self::chooser::{ColumnChooser, Columns, ColumnsIter, FirstColumn, MinRemainingValues, RandomMinRemainingValues, Weighted};
+
+#[derive(Clone)]
 struct Vertex {
-    id: usize,
-    active: bool,
     row: usize,
     col: usize,
     top_id: usize,
@@ -9,51 +22,265 @@ struct Vertex {
     bottom_id: usize
 }
 
+// The matrix is stored sparsely: the vertices vector begins with a header vertex
+// for each column, followed by a header vertex for each row, followed by one
+// vertex for each element (i.e., each 1) that has been set in the matrix.
+//
+// The primary columns come first and must each be covered exactly once by a
+// solution. Any secondary columns follow them, and may be covered at most once.
+// Columns may be given other bounds on the number of times they are covered,
+// and elements of secondary columns may be given colours, in which case rows
+// with the same colour in a column may share it.
+//
+// The active columns, i.e., the primary columns to be covered exactly once that
+// have yet to be covered, form a circular doubly-linked list through active_next
+// and active_prev, whose root is at index num_cols. Any other primary columns are
+// tracked separately in bounded_cols.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone)]
 pub struct DLX {
     vertices: Vec<Vertex>,
-    active_cols: Vec<bool>,
+    vertex_colours: Vec<Option<usize>>,
+    active_next: Vec<usize>,
+    active_prev: Vec<usize>,
+    bounded_cols: Vec<usize>,
+    col_bounds: Vec<(usize, usize)>,
+    col_cover_counts: Vec<usize>,
+    col_colours: Vec<Option<usize>>,
     col_element_counts: Vec<usize>,
-    edges_initialised: bool,
+    chooser: Box<dyn ColumnChooser>,
+    stats: SearchStats,
+    limits: SearchLimits,
+    aborted: Option<AbortReason>,
+    next_time_check: u64,
+    shared_nodes: Option<SharedNodes>,
     num_rows: usize,
-    num_cols: usize
+    num_cols: usize,
+    num_primary_cols: usize
+}
+
+// Conditions under which a search gives up before it is complete. The timeout
+// applies to the time spent searching, not including any time for which a
+// search is suspended between solutions.
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub timeout: Option<Duration>,
+    pub max_nodes: Option<u64>,
+    pub cancel: Option<Arc<AtomicBool>>
+}
+
+// A count of the nodes searched that is shared between searches of parts of the
+// same tree, so that a node limit applies to them all together, along with how
+// many of this matrix's nodes have been added to it so far.
+#[derive(Clone)]
+struct SharedNodes {
+    total: Arc<AtomicU64>,
+    counted: u64
+}
+
+// Why a search gave up before it was complete.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbortReason {
+    Timeout,
+    NodeLimit,
+    Cancelled
+}
+
+// How many solutions a search should look for before stopping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SolveLimit {
+    First,
+    UpTo(usize),
+    All
+}
+
+impl SolveLimit {
+    pub fn max_solutions(&self) -> usize {
+        match self {
+            SolveLimit::First => 1,
+            SolveLimit::UpTo(n) => *n,
+            SolveLimit::All => usize::MAX
+        }
+    }
+}
+
+impl fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbortReason::Timeout => write!(f, "timed out"),
+            AbortReason::NodeLimit => write!(f, "reached its node limit"),
+            AbortReason::Cancelled => write!(f, "was cancelled")
+        }
+    }
+}
+
+enum Choice {
+    Column(usize),
+    DeadEnd,
+    Solved
+}
+
+// The vertices eliminated and columns covered by a search, in the order in which
+// this happened, so that they can be restored in reverse.
+struct UndoLog {
+    vertices: Vec<usize>,
+    columns: Vec<usize>
+}
+
+// A column being explored by the search, and the row of it currently chosen
+// (elem_id is the column's header vertex before the first row is chosen). The
+// marks record the lengths of the undo log before the row was eliminated, and
+// before any rows already tried were excluded from the column. A column that has
+// already been covered as often as it must be is finally closed, i.e., covered
+// no further.
+struct SearchFrame {
+    col_id: usize,
+    elem_id: usize,
+    vertices_mark: usize,
+    columns_mark: usize,
+    excluded_mark: usize,
+    closed: bool
+}
+
+// The state of a search, which can be suspended between solutions. The depth of
+// the search is limited (see max_search_depth), and each vertex can only be
+// eliminated once at a time. Everything, including the histogram of branching
+// factors, is therefore allocated up front, and the search itself performs no
+// further allocation.
+struct Search {
+    stack: Vec<SearchFrame>,
+    candidate: Vec<usize>,
+    undo: UndoLog,
+    max_depth: usize,
+    started: bool
+}
+
+impl Search {
+    fn new(matrix: &DLX) -> Self {
+        let max_depth = matrix.max_search_depth();
+        let max_covers = matrix.col_bounds.iter().fold(0, |covers: usize, (_, max)| covers.saturating_add(*max));
+        Self {
+            // a frame is pushed for each row chosen, and for each column closed
+            stack: Vec::with_capacity(max_depth + matrix.bounded_cols.len()),
+            candidate: Vec::with_capacity(max_depth),
+            undo: UndoLog{vertices: Vec::with_capacity(matrix.num_elements()),
+                          columns: Vec::with_capacity(max_covers)},
+            max_depth,
+            started: false
+        }
+    }
 }
 
 impl DLX {
+    // the number of nodes between checks of the clock against any timeout
+    const TIME_CHECK_INTERVAL: u64 = 1024;
+
     pub fn new(num_rows: usize, num_cols: usize) -> Self {
+        DLX::new_with_secondary(num_rows, num_cols, 0)
+    }
+
+    // Create a matrix whose last secondary_cols columns are secondary, i.e.,
+    // they need not be covered, and are only excluded once they have been.
+    pub fn new_with_secondary(num_rows: usize, primary_cols: usize, secondary_cols: usize) -> Self {
+        let num_cols = primary_cols + secondary_cols;
+
         // instantiate the matrix
         let mut mat = Self {
-            vertices: vec![],
-            active_cols: vec![false; num_cols],
+            vertices: Vec::with_capacity(num_cols + num_rows),
+            vertex_colours: Vec::with_capacity(num_cols + num_rows),
+            active_next: (0..=num_cols).collect(),
+            active_prev: (0..=num_cols).collect(),
+            bounded_cols: Vec::new(),
+            col_bounds: vec![(1, 1); num_cols],
+            col_cover_counts: vec![0; num_cols],
+            col_colours: vec![None; num_cols],
             col_element_counts: vec![0; num_cols],
-            edges_initialised: false,
+            chooser: Box::new(MinRemainingValues),
+            stats: SearchStats::default(),
+            limits: SearchLimits::default(),
+            aborted: None,
+            next_time_check: 0,
+            shared_nodes: None,
             num_rows,
-            num_cols
+            num_cols,
+            num_primary_cols: primary_cols
         };
 
-        // instantiate vertices
-        for _ in 0..=num_rows {
-            for _ in 0..=num_cols {
-                let v = Vertex{id: 0,
-                               active: false,
-                               row: 0,
-                               col: 0,
-                               top_id: 0,
-                               left_id: 0,
-                               right_id: 0,
-                               bottom_id: 0};
-                mat.vertices.push(v);
+        // instantiate the column header vertices, followed by the row header vertices,
+        // each of which initially links to itself
+        for col in 0..num_cols {
+            mat.push_vertex(0, col);
+            if col < primary_cols {
+                mat.activate_column(col);
+            } else {
+                mat.col_bounds[col] = (0, 1);
             }
         }
+        for row in 0..num_rows {
+            mat.push_vertex(row, 0);
+        }
 
         mat
     }
 
+    fn push_vertex(&mut self, row: usize, col: usize) -> usize {
+        let id = self.vertices.len();
+        self.vertices.push(Vertex{row,
+                                  col,
+                                  top_id: id,
+                                  left_id: id,
+                                  right_id: id,
+                                  bottom_id: id});
+        self.vertex_colours.push(None);
+        id
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.num_cols
+    }
+
+    pub fn num_primary_cols(&self) -> usize {
+        self.num_primary_cols
+    }
+
+    pub fn num_secondary_cols(&self) -> usize {
+        self.num_cols - self.num_primary_cols
+    }
+
+    // statistics for the most recent search of the matrix
+    pub fn stats(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    // set the heuristic used to choose the column to branch on during searches
+    pub fn set_chooser(&mut self, chooser: Box<dyn ColumnChooser>) {
+        self.chooser = chooser;
+    }
+
+    // set the conditions under which subsequent searches will give up
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    // the reason the most recent search gave up, if it did
+    pub fn aborted(&self) -> Option<AbortReason> {
+        self.aborted
+    }
+
+    pub fn num_elements(&self) -> usize {
+        self.vertices.len() - self.num_cols - self.num_rows
+    }
+
     fn row_vertex_id(&self, row: usize) -> usize {
         debug_assert!(row < self.num_rows,
                       "Row {} is out of bounds (num rows: {})",
                       row, self.num_rows);
 
-        (row + 1) * (self.num_cols + 1)
+        self.num_cols + row
     }
 
     fn col_vertex_id(&self, col: usize) -> usize {
@@ -61,225 +288,541 @@ impl DLX {
                       "Col {} is out of bounds (num cols: {})",
                       col, self.num_cols);
 
-        col + 1
+        col
     }
 
-    fn element_vertex_id(&self, row: usize, col: usize) -> usize {
-        debug_assert!(row < self.num_rows,
-                      "Element row {} is out of bounds (num rows: {})",
-                      row, self.num_rows);
-        debug_assert!(col < self.num_cols,
-                      "Element col {} is out of bounds (num cols: {})",
-                      col, self.num_cols);
+    fn element_vertex_id(&self, row: usize, col: usize) -> Option<usize> {
+        assert!(row < self.num_rows,
+                "Element row {} is out of bounds (num rows: {})",
+                row, self.num_rows);
+        assert!(col < self.num_cols,
+                "Element col {} is out of bounds (num cols: {})",
+                col, self.num_cols);
 
-        (col + 1) + (row + 1) * (self.num_cols + 1)
+        // walk along the row looking for an element in the column
+        let row_id = self.row_vertex_id(row);
+        let mut elem_id = self.vertices[row_id].right_id;
+        while elem_id != row_id {
+            if self.vertices[elem_id].col == col {
+                return Some(elem_id);
+            }
+            elem_id = self.vertices[elem_id].right_id;
+        }
+        None
     }
 
+    // Set an element of the matrix. The element is appended to the end of its row,
+    // and to the bottom of its column, so rows are explored in the order in which
+    // they are populated.
     pub fn set_element(&mut self, row: usize, col: usize) {
-        let id = self.element_vertex_id(row, col);
-        self.vertices[id].active = true;
-        self.edges_initialised = false;
+        if self.element_vertex_id(row, col).is_some() {
+            return;
+        }
+
+        let row_id = self.row_vertex_id(row);
+        let col_id = self.col_vertex_id(col);
+        let elem_id = self.push_vertex(row, col);
+
+        // link the element in at the end of the row
+        let last_id = self.vertices[row_id].left_id;
+        self.vertices[elem_id].left_id = last_id;
+        self.vertices[elem_id].right_id = row_id;
+        self.vertices[last_id].right_id = elem_id;
+        self.vertices[row_id].left_id = elem_id;
+
+        // link the element in at the bottom of the column
+        let bottom_id = self.vertices[col_id].top_id;
+        self.vertices[elem_id].top_id = bottom_id;
+        self.vertices[elem_id].bottom_id = col_id;
+        self.vertices[bottom_id].bottom_id = elem_id;
+        self.vertices[col_id].top_id = elem_id;
+
+        self.col_element_counts[col] += 1;
     }
 
-    fn initialise_edges(&mut self) {
-        // set the left-right/row info for each active vertex
-        for row in 0..self.num_rows {
-            let mut first: Option<usize> = None;
-            let mut last: Option<usize> = None;
+    // Set an element of a secondary column with a colour. Rows with the same colour
+    // in the column may be chosen together, but not with rows of another colour
+    // or with no colour in the column. A coloured column cannot also be covered
+    // more than once. Panics if the column is primary or may be covered more than
+    // once (ExactCover checks for both, and gives an error instead).
+    pub fn set_coloured_element(&mut self, row: usize, col: usize, colour: usize) {
+        assert!(col >= self.num_primary_cols,
+                "Col {} is not a secondary column, so cannot be coloured",
+                col);
+        assert!(self.col_bounds[col].1 == 1,
+                "Col {} may be covered more than once, so cannot be coloured",
+                col);
+
+        self.set_element(row, col);
+        if let Some(elem_id) = self.element_vertex_id(row, col) {
+            self.vertex_colours[elem_id] = Some(colour);
+        }
+    }
 
-            for col in 0..self.num_cols {
-                let elem_id = self.element_vertex_id(row, col);
+    // Set the minimum and maximum number of times a solution may cover a column.
+    // Secondary columns never need to be covered, so their minimum must be zero.
+    // No column can be covered more times than there are rows, so a larger
+    // maximum is taken to be the number of rows.
+    // Panics if the bounds are empty, if a secondary column is given a minimum
+    // above zero, or if a coloured column is allowed to be covered more than once.
+    pub fn set_column_bounds(&mut self, col: usize, min: usize, max: usize) {
+        assert!(min <= max && max > 0,
+                "Invalid bounds [{}, {}] for col {}",
+                min, max, col);
+        assert!(col < self.num_primary_cols || min == 0,
+                "Secondary col {} cannot have a minimum of {}",
+                col, min);
+
+        let col_id = self.col_vertex_id(col);
+        let mut elem_id = self.vertices[col_id].bottom_id;
+        while elem_id != col_id {
+            assert!(max == 1 || self.vertex_colours[elem_id].is_none(),
+                    "Col {} has coloured elements, so cannot be covered more than once",
+                    col);
+            elem_id = self.vertices[elem_id].bottom_id;
+        }
 
-                if self.vertices[elem_id].active {
-                    self.vertices[elem_id].id = elem_id;
-                    self.vertices[elem_id].row = row;
+        // primary columns not covered exactly once are chosen separately by the search
+        if self.is_exactly_once(col) {
+            self.deactivate_column(col);
+        }
+        self.col_bounds[col_id] = (min, max.min(self.num_rows.max(min)));
+        self.bounded_cols.retain(|bounded_col| *bounded_col != col);
+        if self.is_exactly_once(col) {
+            self.activate_column(col);
+        } else if col < self.num_primary_cols {
+            self.bounded_cols.push(col);
+        }
+    }
 
-                    if let Some(last_id) = last {
-                        self.vertices[elem_id].left_id = last_id;
-                        self.vertices[last_id].right_id = elem_id;
-                    } else {
-                        first = Some(elem_id);
-                    }
-                    last = Some(elem_id);
-                }
-            }
+    // The deepest a search can go. Each row chosen covers at least as many of the
+    // primary columns as the row that covers the fewest of them (a row covering
+    // none cannot be chosen), and they can only be covered so many times.
+    fn max_search_depth(&self) -> usize {
+        let primary_covers = self.col_bounds[..self.num_primary_cols]
+                                 .iter()
+                                 .fold(0, |covers: usize, (_, max)| covers.saturating_add(*max));
+        let min_row_covers = (0..self.num_rows).map(|row| self.num_primary_elements(row))
+                                               .filter(|count| *count > 0)
+                                               .min()
+                                               .unwrap_or(1);
+        (primary_covers / min_row_covers).min(self.num_rows)
+    }
 
-            if let Some(first_id) = first {
-                if let Some(last_id) = last {
-                    // initialise and set the row vertex
-                    let row_id = self.row_vertex_id(row);
-                    self.vertices[row_id].left_id = last_id;
-                    self.vertices[row_id].right_id = first_id;
-
-                    // set left/right ids to the row vertex
-                    // first and last vertex in the row
-                    self.vertices[first_id].left_id = row_id;
-                    self.vertices[last_id].right_id = row_id;
-                }
+    // the number of primary columns a row covers
+    fn num_primary_elements(&self, row: usize) -> usize {
+        let row_id = self.row_vertex_id(row);
+        let mut count = 0;
+        let mut elem_id = self.vertices[row_id].right_id;
+        while elem_id != row_id {
+            if self.vertices[elem_id].col < self.num_primary_cols {
+                count += 1;
             }
+            elem_id = self.vertices[elem_id].right_id;
         }
+        count
+    }
 
-        // set the top-bottom/col info for each active vertex
-        for col in 0..self.num_cols {
-            let mut first: Option<usize> = None;
-            let mut last: Option<usize> = None;
+    // whether a column is a primary column to be covered exactly once
+    fn is_exactly_once(&self, col: usize) -> bool {
+        col < self.num_primary_cols && self.col_bounds[col] == (1, 1)
+    }
 
-            for row in 0..self.num_rows {
-                let elem_id = self.element_vertex_id(row, col);
+    // Link a column into the list of active columns, in column order.
+    fn activate_column(&mut self, col: usize) {
+        let root = self.num_cols;
+        let mut next = self.active_next[root];
+        while next != root && next < col {
+            next = self.active_next[next];
+        }
+        self.active_prev[col] = self.active_prev[next];
+        self.active_next[col] = next;
+        self.reactivate_column(col);
+    }
 
-                if self.vertices[elem_id].active {
-                    // set the col for the vertex
-                    self.vertices[elem_id].col = col;
+    // Unlink a column from the list of active columns, leaving its own links as
+    // they were, so that it can be relinked where it was.
+    fn deactivate_column(&mut self, col: usize) {
+        let prev = self.active_prev[col];
+        let next = self.active_next[col];
+        self.active_next[prev] = next;
+        self.active_prev[next] = prev;
+    }
 
-                    // increment the count of nodes in this column
-                    self.col_element_counts[col] += 1;
+    fn reactivate_column(&mut self, col: usize) {
+        let prev = self.active_prev[col];
+        let next = self.active_next[col];
+        self.active_next[prev] = col;
+        self.active_prev[next] = col;
+    }
 
-                    // set top/right indices for each element
-                    if let Some(last_id) = last {
-                        self.vertices[elem_id].top_id = last_id;
-                        self.vertices[last_id].bottom_id = elem_id;
-                    } else {
-                        first = Some(elem_id);
-                    }
-                    last = Some(elem_id);
-                }
-            }
+    pub fn column_bounds(&self, col: usize) -> (usize, usize) {
+        self.col_bounds[self.col_vertex_id(col)]
+    }
 
-            if let Some(first_id) = first {
-                if let Some(last_id) = last {
-                    // initialise and set the row vertex
-                    let col_id = self.col_vertex_id(col);
-                    self.active_cols[col] = true;
-                    self.vertices[col_id].top_id = last_id;
-                    self.vertices[col_id].bottom_id = first_id;
-
-                    // set left/right ids to the col vertex
-                    // first and last vertex in the col
-                    self.vertices[first_id].top_id = col_id;
-                    self.vertices[last_id].bottom_id = col_id;
-                }
+    // Set all the elements of a row, given as a list of the columns it occupies.
+    pub fn add_row(&mut self, row: usize, cols: &[usize]) {
+        for col in cols {
+            self.set_element(row, *col);
+        }
+    }
+
+    // Shuffle the order of the rows within each column, so that searches explore
+    // them in a random order rather than the order in which they were populated.
+    // This must be done before any rows have been eliminated.
+    pub fn shuffle_rows(&mut self, rng: &mut Rng) {
+        let mut num_linked = 0;
+        let mut elem_ids = Vec::new();
+        for col in 0..self.num_cols {
+            let col_id = self.col_vertex_id(col);
+            elem_ids.clear();
+            let mut elem_id = self.vertices[col_id].bottom_id;
+            while elem_id != col_id {
+                elem_ids.push(elem_id);
+                elem_id = self.vertices[elem_id].bottom_id;
+            }
+            num_linked += elem_ids.len();
+            rng.shuffle(&mut elem_ids);
+
+            // relink the column in its new order
+            let mut top_id = col_id;
+            for elem_id in elem_ids.iter().copied().chain(Some(col_id)) {
+                self.vertices[top_id].bottom_id = elem_id;
+                self.vertices[elem_id].top_id = top_id;
+                top_id = elem_id;
             }
         }
+        assert_eq!(self.num_elements(), num_linked,
+                   "Rows cannot be shuffled once any have been eliminated");
+    }
+
+    pub fn find_solutions(&mut self, limit: SolveLimit) -> Vec<Vec<usize>> {
+        self.solutions().take(limit.max_solutions()).collect()
+    }
+
+    pub fn find_solutions_with_stats(&mut self, limit: SolveLimit) -> (Vec<Vec<usize>>, SearchStats) {
+        let solutions = self.find_solutions(limit);
+        (solutions, self.stats.clone())
+    }
 
-        // set the internal flag to avoid re-calling this
-        // method every time the cover method is called
-        self.edges_initialised = true;
+    // Lazily iterate over the solutions of the matrix. The matrix is restored to
+    // its original state when the iterator is dropped.
+    pub fn solutions(&mut self) -> Solutions<&mut DLX> {
+        Solutions::new(self)
     }
 
-    pub fn find_solutions(&mut self, terminate_on_first: bool) -> Vec<Vec<usize>> {
-        if !self.edges_initialised {
-            self.initialise_edges();
+    // As for solutions, but the iterator takes ownership of the matrix.
+    pub fn into_solutions(self) -> Solutions<DLX> {
+        Solutions::new(self)
+    }
+
+    // Count the solutions of the matrix (up to an optional limit), without
+    // storing any of them.
+    pub fn count_solutions(&mut self, limit: Option<u64>) -> u64 {
+        let mut search = Search::new(self);
+        let mut count = 0;
+        let below_limit = |count| match limit {
+            Some(limit) => count < limit,
+            None => true
+        };
+        while below_limit(count) && self.next_solution(&mut search) {
+            count += 1;
         }
-        let mut solutions: Vec<Vec<usize>> = Vec::new();
-        let mut candidate: Vec<usize> = Vec::new();
-        self.reduce_matrix(&mut candidate, &mut solutions, terminate_on_first);
-        solutions
+        self.abandon(&mut search);
+        count
     }
 
-    fn reduce_matrix(&mut self,
-                     candidate: &mut Vec<usize>,
-                     solutions: &mut Vec<Vec<usize>>,
-                     terminate_on_first: bool) {
+    fn choose_column(&mut self) -> Choice {
+        let columns = Columns::new(&self.active_next,
+                                   &self.bounded_cols,
+                                   &self.col_bounds,
+                                   &self.col_cover_counts,
+                                   &self.col_element_counts);
+        match self.chooser.choose(&columns) {
+            // a column that cannot be covered --- this is a dead-end
+            Some(col) if columns.choices(col) == 0 => Choice::DeadEnd,
+            Some(col) => Choice::Column(col),
+            // there were no primary columns left to cover --- we have a solution!
+            None => Choice::Solved
+        }
+    }
 
-        // if we just want one solution, and we already have one, return
-        if solutions.len() > 0 && terminate_on_first {
-            return;
+    // Push a frame for the column chosen at the current node of the search,
+    // returning true if the node is a solution.
+    fn expand(&mut self, search: &mut Search) -> bool {
+        let choice = self.choose_column();
+        let branching_factor = match choice {
+            Choice::Column(col) => self.col_element_counts[col],
+            _ => 0
+        };
+        self.stats.record_node(search.candidate.len(), branching_factor);
+
+        match choice {
+            Choice::Column(col) => {
+                let col_id = self.col_vertex_id(col);
+                search.stack.push(SearchFrame{col_id,
+                                              elem_id: col_id,
+                                              vertices_mark: search.undo.vertices.len(),
+                                              columns_mark: search.undo.columns.len(),
+                                              excluded_mark: search.undo.vertices.len(),
+                                              closed: false});
+                false
+            }
+            Choice::DeadEnd => false,
+            Choice::Solved => true
         }
+    }
 
-        // get the column with the fewest remaining active rows
-        let mut min = self.num_rows;
-        let mut min_col: Option<usize> = None;
-        for col in 0..self.num_cols {
-            let count = self.col_element_counts[col];
-            if count > 0 && count < min {
-                min = count;
-                min_col = Some(col);
+    // Advance the search to its next solution, which is left in search.candidate.
+    // Returns false when there are no more solutions.
+    fn next_solution(&mut self, search: &mut Search) -> bool {
+        let start_time = Instant::now();
+        if !search.started {
+            self.stats = SearchStats::default();
+            let max_factor = self.col_element_counts.iter().copied().max().unwrap_or(0);
+            self.stats.reserve_branching(search.max_depth, max_factor);
+            self.aborted = None;
+            self.next_time_check = 0;
+            if let Some(shared) = &mut self.shared_nodes {
+                shared.counted = 0;
             }
+        } else if self.aborted.is_some() {
+            return false;
+        }
+        let found = self.search_next(search, start_time);
+        self.stats.time += start_time.elapsed();
+        if !found {
+            self.stats.trim_branching();
         }
+        found
+    }
 
-        // if there is a column to explore, then explore it
-        if let Some(col) = min_col {
+    fn search_next(&mut self, search: &mut Search, start_time: Instant) -> bool {
+        if !search.started {
+            search.started = true;
+            if self.expand(search) {
+                return true;
+            }
+        }
 
-            // work our way down the column
-            let col_id = self.col_vertex_id(col);
-            let mut elem_id = self.vertices[col_id].bottom_id;
+        loop {
+            // give up if any of the limits on the search have been reached
+            if let Some(reason) = self.limit_reached(start_time) {
+                self.aborted = Some(reason);
+                return false;
+            }
 
-            while elem_id != col_id {
-                let row = self.vertices[elem_id].row;
-                let (eliminated_vertices, eliminated_columns) = self.eliminate_row(row);
-                candidate.push(row);
+            let mut frame = match search.stack.pop() {
+                Some(frame) => frame,
+                None => return false
+            };
 
-                self.reduce_matrix(candidate, solutions, terminate_on_first);
+            // undo closing the column, which is the last thing tried
+            if frame.closed {
+                self.restore(&mut search.undo, frame.excluded_mark, frame.columns_mark);
+                self.stats.backtracks += 1;
+                continue;
+            }
 
-                candidate.pop();
-                self.restore(eliminated_vertices, eliminated_columns);
+            // undo the row explored last time this frame was visited
+            let col = self.vertices[frame.col_id].col;
+            if frame.elem_id != frame.col_id {
+                self.restore(&mut search.undo, frame.vertices_mark, frame.columns_mark);
+                search.candidate.pop();
+                self.stats.backtracks += 1;
+
+                // a column covered more than once could be covered by the same rows
+                // in a different order, so rule out the rows already tried
+                if self.col_bounds[col].1 > 1 {
+                    let row = self.vertices[frame.elem_id].row;
+                    self.exclude_row(row, &mut search.undo);
+                }
+            }
 
-                // update the elem_id for the next iteration
-                elem_id = self.vertices[elem_id].bottom_id;
+            // move down the column to the next row, unless it is exhausted
+            frame.elem_id = self.vertices[frame.elem_id].bottom_id;
+            if frame.elem_id == frame.col_id {
+                if self.col_cover_counts[col] >= self.col_bounds[col].0 {
+                    frame.closed = true;
+                    frame.columns_mark = search.undo.columns.len();
+                    self.close_column(col, &mut search.undo);
+                    search.stack.push(frame);
+                    if self.expand(search) {
+                        return true;
+                    }
+                } else {
+                    let columns_mark = search.undo.columns.len();
+                    self.restore(&mut search.undo, frame.excluded_mark, columns_mark);
+                }
+                continue;
             }
-        } else {
-            // there were no columns. Is the matrix empty?
-            // if not, return empty-handed --- this is a dead-end.
-            for col in self.active_cols.iter() {
-                if *col {
-                    return;
+
+            let row = self.vertices[frame.elem_id].row;
+            frame.vertices_mark = search.undo.vertices.len();
+            frame.columns_mark = search.undo.columns.len();
+            self.eliminate_row_into(row, &mut search.undo);
+            search.candidate.push(row);
+            search.stack.push(frame);
+
+            if self.expand(search) {
+                return true;
+            }
+        }
+    }
+
+    fn limit_reached(&mut self, start_time: Instant) -> Option<AbortReason> {
+        if let Some(cancel) = &self.limits.cancel {
+            if cancel.load(Ordering::Relaxed) {
+                return Some(AbortReason::Cancelled);
+            }
+        }
+        if let Some(max_nodes) = self.limits.max_nodes {
+            if self.nodes_searched() >= max_nodes {
+                return Some(AbortReason::NodeLimit);
+            }
+        }
+        if let Some(timeout) = self.limits.timeout {
+            // reading the clock is relatively expensive, so only do so periodically
+            if self.stats.nodes >= self.next_time_check {
+                self.next_time_check = self.stats.nodes + DLX::TIME_CHECK_INTERVAL;
+                if self.stats.time + start_time.elapsed() >= timeout {
+                    return Some(AbortReason::Timeout);
                 }
             }
-            // the matrix is empty --- we have a solution!
-            solutions.push(candidate.clone());
         }
+        None
     }
 
-    pub fn eliminate_row(&mut self, row: usize) -> (Vec<usize>, Vec<usize>) {
-        if !self.edges_initialised {
-            self.initialise_edges();
+    // The number of nodes searched so far, including by any other searches
+    // this matrix shares a count with.
+    fn nodes_searched(&mut self) -> u64 {
+        match &mut self.shared_nodes {
+            Some(shared) => {
+                let new_nodes = self.stats.nodes - shared.counted;
+                shared.counted = self.stats.nodes;
+                shared.total.fetch_add(new_nodes, Ordering::SeqCst) + new_nodes
+            }
+            None => self.stats.nodes
+        }
+    }
+
+    // Restore all the rows eliminated by a suspended search.
+    fn abandon(&mut self, search: &mut Search) {
+        if let Some(frame) = search.stack.first() {
+            let (vertices_mark, columns_mark) = (frame.excluded_mark, frame.columns_mark);
+            self.restore(&mut search.undo, vertices_mark, columns_mark);
         }
+        search.stack.clear();
+        search.candidate.clear();
+        self.stats.trim_branching();
+    }
+
+    // Eliminate a row from the matrix permanently, as though it had been chosen
+    // as part of every solution, returning the vertices and columns eliminated.
+    pub fn eliminate_row(&mut self, row: usize) -> (Vec<usize>, Vec<usize>) {
+        let mut undo = UndoLog{vertices: Vec::new(), columns: Vec::new()};
+        self.eliminate_row_into(row, &mut undo);
+        (undo.vertices, undo.columns)
+    }
 
-        let mut eliminated_vertices: Vec<usize> = Vec::new();
-        let mut eliminated_columns: Vec<usize> = Vec::new();
+    // Remove a row from the matrix permanently, so that it cannot be chosen as
+    // part of any solution, returning the vertices removed. Removing a row that
+    // has already been eliminated has no effect.
+    pub fn remove_row(&mut self, row: usize) -> Vec<usize> {
+        let mut undo = UndoLog{vertices: Vec::new(), columns: Vec::new()};
+        self.exclude_row(row, &mut undo);
+        undo.vertices
+    }
 
+    fn eliminate_row_into(&mut self, row: usize, undo: &mut UndoLog) {
         let row_id = self.row_vertex_id(row);
         let mut row_elem_id = self.vertices[row_id].right_id;
 
         while row_elem_id != row_id {
             let col = self.vertices[row_elem_id].col;
-            let col_id = self.col_vertex_id(col);
 
-            // cover the column
-            eliminated_columns.push(col);
-            self.active_cols[col] = false;
+            match self.vertex_colours[row_elem_id] {
+                // rows of other colours can no longer share the column
+                Some(colour) => if self.col_colours[col] != Some(colour) {
+                    undo.columns.push(col);
+                    self.col_cover_counts[col] += 1;
+                    self.col_colours[col] = Some(colour);
+                    self.eliminate_column(col, Some(colour), undo);
+                },
+                // once the column has been covered as often as it can be, no other
+                // rows may cover it
+                None => {
+                    undo.columns.push(col);
+                    self.col_cover_counts[col] += 1;
+                    if self.col_cover_counts[col] == self.col_bounds[col].1 {
+                        if self.is_exactly_once(col) {
+                            self.deactivate_column(col);
+                        }
+                        self.eliminate_column(col, None, undo);
+                    }
+                }
+            }
+            row_elem_id = self.vertices[row_elem_id].right_id;
+        }
+
+        // the row itself remains if none of its columns were eliminated
+        self.exclude_row(row, undo);
+    }
 
-            // cover elements in the columns of this row
-            let mut col_elem_id = self.vertices[col_id].bottom_id;
-            while col_elem_id != col_id {
+    // Eliminate the rows in a column, other than those with the given colour in it.
+    fn eliminate_column(&mut self, col: usize, keep_colour: Option<usize>, undo: &mut UndoLog) {
+        let col_id = self.col_vertex_id(col);
+        let mut col_elem_id = self.vertices[col_id].bottom_id;
+        while col_elem_id != col_id {
+            if keep_colour.is_none() || self.vertex_colours[col_elem_id] != keep_colour {
                 self.cover_vertex(col_elem_id);
-                eliminated_vertices.push(col_elem_id);
+                undo.vertices.push(col_elem_id);
 
                 // cover elements on the row intersecting this column element
                 let intersect_row = self.vertices[col_elem_id].row;
+                self.exclude_row(intersect_row, undo);
+            }
+            col_elem_id = self.vertices[col_elem_id].bottom_id;
+        }
+    }
 
-                let intersect_row_id = self.row_vertex_id(intersect_row);
-                let mut intersect_row_elem_id = self.vertices[intersect_row_id].right_id;
+    // Eliminate the rows remaining in a column, and count it as fully covered.
+    fn close_column(&mut self, col: usize, undo: &mut UndoLog) {
+        self.eliminate_column(col, None, undo);
+        while self.col_cover_counts[col] < self.col_bounds[col].1 {
+            undo.columns.push(col);
+            self.col_cover_counts[col] += 1;
+        }
+    }
 
-                while intersect_row_elem_id != intersect_row_id {
-                    self.cover_vertex(intersect_row_elem_id);
-                    eliminated_vertices.push(intersect_row_elem_id);
-                    intersect_row_elem_id = self.vertices[intersect_row_elem_id].right_id;
-                }
-                col_elem_id = self.vertices[col_elem_id].bottom_id;
-            }
+    // Cover the elements remaining in a row, so that it can no longer be chosen.
+    fn exclude_row(&mut self, row: usize, undo: &mut UndoLog) {
+        let row_id = self.row_vertex_id(row);
+        let mut row_elem_id = self.vertices[row_id].right_id;
+        while row_elem_id != row_id {
+            self.cover_vertex(row_elem_id);
+            undo.vertices.push(row_elem_id);
             row_elem_id = self.vertices[row_elem_id].right_id;
         }
-
-        (eliminated_vertices, eliminated_columns)
     }
 
-    fn restore(&mut self, eliminated_vertices: Vec<usize>, eliminated_columns: Vec<usize>) {
-        for vertex in eliminated_vertices {
-            self.uncover_vertex(vertex);
+    // Restore everything eliminated since the undo log had the given lengths.
+    fn restore(&mut self, undo: &mut UndoLog, vertices_mark: usize, columns_mark: usize) {
+        while undo.vertices.len() > vertices_mark {
+            if let Some(vertex) = undo.vertices.pop() {
+                self.uncover_vertex(vertex);
+            }
         }
-        for col in eliminated_columns {
-            self.active_cols[col] = true;
+        while undo.columns.len() > columns_mark {
+            if let Some(col) = undo.columns.pop() {
+                if self.is_exactly_once(col) {
+                    self.reactivate_column(col);
+                }
+                self.col_cover_counts[col] -= 1;
+                if self.col_cover_counts[col] == 0 {
+                    self.col_colours[col] = None;
+                }
+            }
         }
     }
 
@@ -296,6 +839,7 @@ impl DLX {
 
         let col = self.vertices[id].col;
         self.col_element_counts[col] -= 1;
+        self.stats.covers += 1;
     }
 
     fn uncover_vertex(&mut self, id: usize) {
@@ -311,49 +855,86 @@ impl DLX {
 
         let col = self.vertices[id].col;
         self.col_element_counts[col] += 1;
+        self.stats.uncovers += 1;
     }
 }
 
+pub struct Solutions<M: BorrowMut<DLX>> {
+    matrix: M,
+    search: Search
+}
+
+impl<M: BorrowMut<DLX>> Solutions<M> {
+    fn new(matrix: M) -> Self {
+        let search = Search::new(matrix.borrow());
+        Self {
+            matrix,
+            search
+        }
+    }
+}
+
+impl<M: BorrowMut<DLX>> Solutions<M> {
+    // statistics for the search so far
+    pub fn stats(&self) -> &SearchStats {
+        self.matrix.borrow().stats()
+    }
+
+    // the reason the search gave up, if it did
+    pub fn aborted(&self) -> Option<AbortReason> {
+        self.matrix.borrow().aborted()
+    }
+}
+
+impl<M: BorrowMut<DLX>> Iterator for Solutions<M> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.matrix.borrow_mut().next_solution(&mut self.search) {
+            Some(self.search.candidate.clone())
+        } else {
+            None
+        }
+    }
+}
+
+impl<M: BorrowMut<DLX>> Drop for Solutions<M> {
+    fn drop(&mut self) {
+        self.matrix.borrow_mut().abandon(&mut self.search);
+    }
+}
+
+#[cfg(test)]
 mod tests {
-    use super::DLX;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{AbortReason, FirstColumn, RandomMinRemainingValues, Rng, SearchLimits, SolveLimit, Weighted, DLX};
 
     #[test]
     fn test_matrix_new() {
         let num_rows = 3;
-        let num_cols = 3;
-        let num_vertices = (num_rows + 1) * (num_cols + 1);
+        let num_cols = 4;
         let mat = DLX::new(num_rows, num_cols);
-        assert_eq!(num_vertices, mat.vertices.len());
-    }
-
-    #[test]
-    fn test_matrix_element_vertex_id() {
-        let mat = DLX::new(3, 3);
-        assert_eq!(5, mat.element_vertex_id(0, 0));
-        assert_eq!(6, mat.element_vertex_id(0, 1));
-        assert_eq!(7, mat.element_vertex_id(0, 2));
-        assert_eq!(9, mat.element_vertex_id(1, 0));
-        assert_eq!(10, mat.element_vertex_id(1, 1));
-        assert_eq!(11, mat.element_vertex_id(1, 2));
-        assert_eq!(13, mat.element_vertex_id(2, 0));
-        assert_eq!(14, mat.element_vertex_id(2, 1));
-        assert_eq!(15, mat.element_vertex_id(2, 2));
+        assert_eq!(num_rows + num_cols, mat.vertices.len());
+        assert_eq!(0, mat.num_elements());
     }
 
     #[test]
     fn test_matrix_row_vertex_id() {
         let mat = DLX::new(3, 3);
-        assert_eq!(4, mat.row_vertex_id(0));
-        assert_eq!(8, mat.row_vertex_id(1));
-        assert_eq!(12, mat.row_vertex_id(2));
+        assert_eq!(3, mat.row_vertex_id(0));
+        assert_eq!(4, mat.row_vertex_id(1));
+        assert_eq!(5, mat.row_vertex_id(2));
     }
 
     #[test]
     fn test_matrix_col_vertex_id() {
         let mat = DLX::new(3, 3);
-        assert_eq!(1, mat.col_vertex_id(0));
-        assert_eq!(2, mat.col_vertex_id(1));
-        assert_eq!(3, mat.col_vertex_id(2));
+        assert_eq!(0, mat.col_vertex_id(0));
+        assert_eq!(1, mat.col_vertex_id(1));
+        assert_eq!(2, mat.col_vertex_id(2));
     }
 
     #[test]
@@ -374,19 +955,23 @@ mod tests {
     fn test_matrix_set_element() {
         let mut mat = DLX::new(3, 3);
         mat.set_element(0, 1);
-        assert_eq!(false, mat.vertices[mat.element_vertex_id(0, 0)].active);
-        assert_eq!(true, mat.vertices[mat.element_vertex_id(0, 1)].active);
+        assert_eq!(None, mat.element_vertex_id(0, 0));
+        assert_eq!(Some(6), mat.element_vertex_id(0, 1));
+        assert_eq!(1, mat.num_elements());
+
+        // setting the same element twice has no effect
+        mat.set_element(0, 1);
+        assert_eq!(1, mat.num_elements());
     }
 
     #[test]
     fn test_matrix_indices_one_element() {
         let mut mat = DLX::new(6, 3);
         mat.set_element(3, 1);
-        mat.initialise_edges();
 
         let col1 = mat.col_vertex_id(1);
         let row3 = mat.row_vertex_id(3);
-        let elem_i3_j1 = mat.element_vertex_id(3, 1);
+        let elem_i3_j1 = mat.element_vertex_id(3, 1).unwrap();
 
         assert_eq!(col1, mat.vertices[elem_i3_j1].top_id, "(3, 1) top");
         assert_eq!(col1, mat.vertices[elem_i3_j1].bottom_id, "(3, 1) bottom");
@@ -403,12 +988,11 @@ mod tests {
         mat.set_element(1, 1);
         mat.set_element(3, 1);
         mat.set_element(5, 1);
-        mat.initialise_edges();
 
         let col1 = mat.col_vertex_id(1);
-        let elem_i1_j1 = mat.element_vertex_id(1, 1);
-        let elem_i3_j1 = mat.element_vertex_id(3, 1);
-        let elem_i5_j1 = mat.element_vertex_id(5, 1);
+        let elem_i1_j1 = mat.element_vertex_id(1, 1).unwrap();
+        let elem_i3_j1 = mat.element_vertex_id(3, 1).unwrap();
+        let elem_i5_j1 = mat.element_vertex_id(5, 1).unwrap();
 
         // check col vertex
         assert_eq!(elem_i5_j1, mat.vertices[col1].top_id, "col top");
@@ -432,15 +1016,12 @@ mod tests {
     #[test]
     fn test_matrix_row_indices() {
         let mut mat = DLX::new(3, 6);
-        mat.set_element(1, 1);
-        mat.set_element(1, 3);
-        mat.set_element(1, 5);
-        mat.initialise_edges();
+        mat.add_row(1, &[1, 3, 5]);
 
         let row1 = mat.row_vertex_id(1);
-        let elem_i1_j1 = mat.element_vertex_id(1, 1);
-        let elem_i1_j3 = mat.element_vertex_id(1, 3);
-        let elem_i1_j5 = mat.element_vertex_id(1, 5);
+        let elem_i1_j1 = mat.element_vertex_id(1, 1).unwrap();
+        let elem_i1_j3 = mat.element_vertex_id(1, 3).unwrap();
+        let elem_i1_j5 = mat.element_vertex_id(1, 5).unwrap();
 
         // check row vertex
         assert_eq!(elem_i1_j5, mat.vertices[row1].left_id, "row left");
@@ -466,12 +1047,11 @@ mod tests {
         mat.set_element(1, 1);
         mat.set_element(3, 1);
         mat.set_element(5, 1);
-        mat.initialise_edges();
 
         let col1 = mat.col_vertex_id(1);
-        let elem_i1_j1 = mat.element_vertex_id(1, 1);
-        let elem_i3_j1 = mat.element_vertex_id(3, 1);
-        let elem_i5_j1 = mat.element_vertex_id(5, 1);
+        let elem_i1_j1 = mat.element_vertex_id(1, 1).unwrap();
+        let elem_i3_j1 = mat.element_vertex_id(3, 1).unwrap();
+        let elem_i5_j1 = mat.element_vertex_id(5, 1).unwrap();
 
         mat.cover_vertex(elem_i1_j1);
 
@@ -497,12 +1077,11 @@ mod tests {
         mat.set_element(1, 1);
         mat.set_element(3, 1);
         mat.set_element(5, 1);
-        mat.initialise_edges();
 
         let col1 = mat.col_vertex_id(1);
-        let elem_i1_j1 = mat.element_vertex_id(1, 1);
-        let elem_i3_j1 = mat.element_vertex_id(3, 1);
-        let elem_i5_j1 = mat.element_vertex_id(5, 1);
+        let elem_i1_j1 = mat.element_vertex_id(1, 1).unwrap();
+        let elem_i3_j1 = mat.element_vertex_id(3, 1).unwrap();
+        let elem_i5_j1 = mat.element_vertex_id(5, 1).unwrap();
 
         mat.cover_vertex(elem_i1_j1);
         mat.cover_vertex(elem_i3_j1);
@@ -523,27 +1102,378 @@ mod tests {
         mat.uncover_vertex(elem_i5_j1);
     }
 
-    // TODO: more tests
-    // fn example_matrix() -> Matrix {
-    //     let mut mat = DLX::new(9, 6);
-    //     mat.set_element(0, 0);
-    //     mat.set_element(0, 3);
-    //     mat.set_element(1, 1);
-    //     mat.set_element(1, 3);
-    //     mat.set_element(2, 2);
-    //     mat.set_element(2, 3);
-    //     mat.set_element(3, 0);
-    //     mat.set_element(3, 4);
-    //     mat.set_element(4, 1);
-    //     mat.set_element(4, 4);
-    //     mat.set_element(5, 2);
-    //     mat.set_element(5, 4);
-    //     mat.set_element(6, 0);
-    //     mat.set_element(6, 5);
-    //     mat.set_element(7, 1);
-    //     mat.set_element(7, 5);
-    //     mat.set_element(8, 2);
-    //     mat.set_element(8, 5);
-    //     mat
-    // }
-}
\ No newline at end of file
+    fn example_matrix() -> DLX {
+        let mut mat = DLX::new(9, 6);
+        mat.add_row(0, &[0, 3]);
+        mat.add_row(1, &[1, 3]);
+        mat.add_row(2, &[2, 3]);
+        mat.add_row(3, &[0, 4]);
+        mat.add_row(4, &[1, 4]);
+        mat.add_row(5, &[2, 4]);
+        mat.add_row(6, &[0, 5]);
+        mat.add_row(7, &[1, 5]);
+        mat.add_row(8, &[2, 5]);
+        mat
+    }
+
+    // the rows place each of n values at each of n positions, so that the
+    // solutions are the permutations of the values
+    fn permutation_matrix(n: usize) -> DLX {
+        let mut mat = DLX::new(n * n, 2 * n);
+        for pos in 0..n {
+            for val in 0..n {
+                mat.add_row(pos * n + val, &[pos, n + val]);
+            }
+        }
+        mat
+    }
+
+    #[test]
+    fn test_find_solutions() {
+        let mut mat = example_matrix();
+        assert_eq!(1, mat.find_solutions(SolveLimit::First).len());
+        assert_eq!(4, mat.find_solutions(SolveLimit::UpTo(4)).len());
+        assert_eq!(6, mat.find_solutions(SolveLimit::UpTo(10)).len());
+        assert_eq!(6, mat.find_solutions(SolveLimit::All).len());
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let mut mat = example_matrix();
+        assert_eq!(6, mat.count_solutions(None));
+        assert_eq!(4, mat.count_solutions(Some(4)));
+        assert_eq!(0, mat.count_solutions(Some(0)));
+        assert_eq!(6, mat.count_solutions(Some(10)));
+    }
+
+    #[test]
+    fn test_shuffle_rows() {
+        let mut mat = example_matrix();
+        let mut solutions = mat.find_solutions(SolveLimit::All);
+        solutions.sort();
+
+        // the same seed always gives the same order, and the same solutions are
+        // found in some order whatever the seed
+        let mut orders = Vec::new();
+        for seed in 0..8 {
+            let mut shuffled = example_matrix();
+            shuffled.shuffle_rows(&mut Rng::new(seed));
+            let mut shuffled_solutions = shuffled.find_solutions(SolveLimit::All);
+            let mut again = example_matrix();
+            again.shuffle_rows(&mut Rng::new(seed));
+            assert_eq!(shuffled_solutions, again.find_solutions(SolveLimit::All));
+            orders.push(shuffled_solutions.clone());
+            shuffled_solutions.sort();
+            assert_eq!(solutions, shuffled_solutions);
+        }
+        assert!(orders.iter().any(|order| *order != orders[0]));
+    }
+
+    #[test]
+    fn test_remove_row() {
+        let mut mat = example_matrix();
+        assert_eq!(2, mat.remove_row(0).len());
+        assert_eq!(4, mat.count_solutions(None));
+        assert!(mat.find_solutions(SolveLimit::All).iter().all(|solution| !solution.contains(&0)));
+
+        // removing a row that has already been eliminated changes nothing
+        mat.eliminate_row(4);
+        assert!(mat.remove_row(1).is_empty());
+        assert_eq!(vec![vec![6, 2]], mat.find_solutions(SolveLimit::All));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_shuffle_rows_panic() {
+        let mut mat = example_matrix();
+        mat.eliminate_row(0);
+        mat.shuffle_rows(&mut Rng::new(0));
+    }
+
+    #[test]
+    fn test_secondary_columns() {
+        // column 2 is secondary, so rows 0 and 1 cannot both be chosen, but it
+        // need not be covered at all
+        let mut mat = DLX::new_with_secondary(4, 2, 1);
+        mat.add_row(0, &[0, 2]);
+        mat.add_row(1, &[1, 2]);
+        mat.add_row(2, &[0]);
+        mat.add_row(3, &[1]);
+        assert_eq!(3, mat.num_cols());
+        assert_eq!(1, mat.num_secondary_cols());
+
+        let mut solutions = mat.find_solutions(SolveLimit::All);
+        for solution in solutions.iter_mut() {
+            solution.sort();
+        }
+        solutions.sort();
+        assert_eq!(vec![vec![0, 3], vec![1, 2], vec![2, 3]], solutions);
+    }
+
+    #[test]
+    fn test_find_solutions_with_stats() {
+        let mut mat = example_matrix();
+        let (solutions, stats) = mat.find_solutions_with_stats(SolveLimit::All);
+        assert_eq!(6, solutions.len());
+
+        // 1 root node with 3 rows, 3 nodes with 2 rows, 6 nodes with 1 row and 6 solutions
+        assert_eq!(16, stats.nodes);
+        assert_eq!(3, stats.max_depth);
+        assert_eq!(15, stats.backtracks);
+        assert_eq!(stats.covers, stats.uncovers);
+        assert_eq!(vec![vec![0, 0, 0, 1], vec![0, 0, 3], vec![0, 6], vec![6]], stats.branching);
+    }
+
+    #[test]
+    fn test_max_search_depth() {
+        // each row covers two of the six primary columns
+        let mut mat = example_matrix();
+        assert_eq!(3, mat.max_search_depth());
+        assert_eq!(5, permutation_matrix(5).max_search_depth());
+
+        // the histogram is sized for the whole search before it starts, and
+        // trimmed to what was used once it ends
+        let mut solutions = mat.solutions();
+        assert!(solutions.next().is_some());
+        assert_eq!(4, solutions.stats().branching.len());
+        drop(solutions);
+        assert_eq!(vec![vec![0, 0, 0, 1], vec![0, 0, 1], vec![0, 1], vec![1]], mat.stats().branching);
+
+        // a column cannot be covered more times than there are rows
+        let mut mat = DLX::new(3, 2);
+        mat.add_row(0, &[0]);
+        mat.add_row(1, &[0]);
+        mat.add_row(2, &[1]);
+        mat.set_column_bounds(0, 0, usize::MAX);
+        assert_eq!(3, mat.max_search_depth());
+        assert_eq!(4, mat.count_solutions(None));
+    }
+
+    #[test]
+    fn test_coloured_columns() {
+        // Knuth's example: primary columns p, q, r and secondary columns x, y, with
+        // rows "p q x y:A", "p r x:A y", "p x:B", "q x:A" and "r y:B"
+        let (a, b) = (0, 1);
+        let mut mat = DLX::new_with_secondary(5, 3, 2);
+        mat.add_row(0, &[0, 1, 3]);
+        mat.set_coloured_element(0, 4, a);
+        mat.add_row(1, &[0, 2, 4]);
+        mat.set_coloured_element(1, 3, a);
+        mat.add_row(2, &[0]);
+        mat.set_coloured_element(2, 3, b);
+        mat.add_row(3, &[1]);
+        mat.set_coloured_element(3, 3, a);
+        mat.add_row(4, &[2]);
+        mat.set_coloured_element(4, 4, b);
+
+        let mut solutions = mat.find_solutions(SolveLimit::All);
+        solutions[0].sort();
+        assert_eq!(vec![vec![1, 3]], solutions);
+        assert_eq!(1, mat.count_solutions_parallel(None, 2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_coloured_primary_column_panic() {
+        let mut mat = DLX::new_with_secondary(1, 1, 1);
+        mat.set_coloured_element(0, 0, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_coloured_column_bounds_panic() {
+        let mut mat = DLX::new_with_secondary(1, 1, 1);
+        mat.set_coloured_element(0, 1, 0);
+        mat.set_column_bounds(1, 0, 2);
+    }
+
+    #[test]
+    fn test_column_bounds() {
+        // column 0 must be covered exactly twice
+        let mut mat = DLX::new(5, 3);
+        mat.add_row(0, &[0, 1]);
+        mat.add_row(1, &[0, 2]);
+        mat.add_row(2, &[0]);
+        mat.add_row(3, &[1]);
+        mat.add_row(4, &[2]);
+        mat.set_column_bounds(0, 2, 2);
+        assert_eq!((2, 2), mat.column_bounds(0));
+
+        // each set of rows is only found once, whatever order it is chosen in
+        let mut solutions = mat.find_solutions(SolveLimit::All);
+        for solution in solutions.iter_mut() {
+            solution.sort();
+        }
+        solutions.sort();
+        assert_eq!(vec![vec![0, 1], vec![0, 2, 4], vec![1, 2, 3]], solutions);
+
+        // and once or twice
+        mat.set_column_bounds(0, 1, 2);
+        assert_eq!(6, mat.count_solutions(None));
+        assert_eq!(6, mat.count_solutions_parallel(None, 2));
+        assert_eq!(6, mat.find_solutions_parallel(SolveLimit::All, 2).len());
+    }
+
+    #[test]
+    fn test_column_choosers() {
+        // the choice of column changes the order of the solutions, but not what they are
+        let sorted = |mut solutions: Vec<Vec<usize>>| {
+            for solution in solutions.iter_mut() {
+                solution.sort();
+            }
+            solutions.sort();
+            solutions
+        };
+        let mut mat = example_matrix();
+        let expected = sorted(mat.find_solutions(SolveLimit::All));
+
+        mat.set_chooser(Box::new(FirstColumn));
+        assert_eq!(expected, sorted(mat.find_solutions(SolveLimit::All)));
+        mat.set_chooser(Box::new(Weighted::new(vec![1.0, 1.0, 1.0, 2.0])));
+        assert_eq!(expected, sorted(mat.find_solutions(SolveLimit::All)));
+
+        // the same seed gives the same search
+        mat.set_chooser(Box::new(RandomMinRemainingValues::new(1)));
+        let random = mat.find_solutions(SolveLimit::All);
+        assert_eq!(expected, sorted(random.clone()));
+        mat.set_chooser(Box::new(RandomMinRemainingValues::new(1)));
+        assert_eq!(random, mat.find_solutions(SolveLimit::All));
+
+        // choosing columns without regard to their rows makes for a larger search
+        let mut mat = DLX::new(4, 3);
+        mat.add_row(0, &[0]);
+        mat.add_row(1, &[0]);
+        mat.add_row(2, &[0]);
+        mat.add_row(3, &[1]);
+        assert_eq!(1, mat.find_solutions_with_stats(SolveLimit::All).1.nodes);
+        mat.set_chooser(Box::new(FirstColumn));
+        assert_eq!(7, mat.find_solutions_with_stats(SolveLimit::All).1.nodes);
+    }
+
+    #[test]
+    fn test_search_limits() {
+        let mut mat = example_matrix();
+        mat.set_limits(SearchLimits{max_nodes: Some(5), ..SearchLimits::default()});
+        let mut solutions = mat.solutions();
+        assert!(solutions.next().is_some());
+        assert_eq!(None, solutions.aborted());
+        assert!(solutions.next().is_none());
+        assert_eq!(Some(AbortReason::NodeLimit), solutions.aborted());
+        assert_eq!(5, solutions.stats().nodes);
+        drop(solutions);
+
+        let cancel = Arc::new(AtomicBool::new(true));
+        mat.set_limits(SearchLimits{cancel: Some(cancel), ..SearchLimits::default()});
+        assert_eq!(0, mat.count_solutions(None));
+        assert_eq!(Some(AbortReason::Cancelled), mat.aborted());
+
+        mat.set_limits(SearchLimits{timeout: Some(Duration::from_secs(0)), ..SearchLimits::default()});
+        assert_eq!(0, mat.count_solutions(None));
+        assert_eq!(Some(AbortReason::Timeout), mat.aborted());
+
+        // the matrix is left intact after an aborted search
+        mat.set_limits(SearchLimits::default());
+        assert_eq!(6, mat.count_solutions(None));
+        assert_eq!(None, mat.aborted());
+    }
+
+    #[test]
+    fn test_find_solutions_parallel() {
+        let mut mat = example_matrix();
+        let solutions = mat.find_solutions(SolveLimit::All);
+        assert_eq!(solutions, mat.find_solutions_parallel(SolveLimit::All, 4));
+        assert_eq!(solutions[..2].to_vec(), mat.find_solutions_parallel(SolveLimit::UpTo(2), 3));
+        assert_eq!(6, mat.count_solutions_parallel(None, 2));
+        assert_eq!(5, mat.count_solutions_parallel(Some(5), 2));
+
+        // the statistics are the same as for a single-threaded search
+        let (_, stats) = mat.find_solutions_with_stats(SolveLimit::All);
+        mat.count_solutions_parallel(None, 4);
+        assert_eq!(stats.nodes, mat.stats().nodes);
+        assert_eq!(stats.branching, mat.stats().branching);
+
+        // a random chooser finds the same solutions as a single-threaded search,
+        // and in the same order every time, however the threads are scheduled
+        let sorted = |mut solutions: Vec<Vec<usize>>| {
+            for solution in solutions.iter_mut() {
+                solution.sort();
+            }
+            solutions.sort();
+            solutions
+        };
+        let mut mat = permutation_matrix(5);
+        mat.set_chooser(Box::new(RandomMinRemainingValues::new(7)));
+        let serial = mat.find_solutions(SolveLimit::All);
+        mat.set_chooser(Box::new(RandomMinRemainingValues::new(7)));
+        let parallel = mat.find_solutions_parallel(SolveLimit::All, 4);
+        assert_eq!(sorted(serial), sorted(parallel.clone()));
+        for _ in 0..10 {
+            mat.set_chooser(Box::new(RandomMinRemainingValues::new(7)));
+            assert_eq!(parallel, mat.find_solutions_parallel(SolveLimit::All, 4));
+        }
+    }
+
+    #[test]
+    fn test_search_limits_parallel() {
+        let mut mat = permutation_matrix(5);
+        let solutions = mat.find_solutions(SolveLimit::All);
+        assert_eq!(120, solutions.len());
+
+        // the node limit applies to all the threads together, and the solutions
+        // found are the first that a single-threaded search would find
+        for max_nodes in [10, 50, 100, 200] {
+            mat.set_limits(SearchLimits{max_nodes: Some(max_nodes), ..SearchLimits::default()});
+            let found = mat.find_solutions_parallel(SolveLimit::All, 4);
+            assert_eq!(Some(AbortReason::NodeLimit), mat.aborted());
+            assert!(mat.stats().nodes <= max_nodes + 4);
+            assert_eq!(solutions[..found.len()].to_vec(), found);
+        }
+
+        // the limits are checked while the search is being split
+        mat.set_limits(SearchLimits{cancel: Some(Arc::new(AtomicBool::new(true))), ..SearchLimits::default()});
+        assert_eq!(0, mat.count_solutions_parallel(None, 4));
+        assert_eq!(Some(AbortReason::Cancelled), mat.aborted());
+        assert_eq!(0, mat.stats().nodes);
+
+        mat.set_limits(SearchLimits{timeout: Some(Duration::from_secs(0)), ..SearchLimits::default()});
+        assert!(mat.find_solutions_parallel(SolveLimit::All, 4).is_empty());
+        assert_eq!(Some(AbortReason::Timeout), mat.aborted());
+
+        mat.set_limits(SearchLimits::default());
+        assert_eq!(solutions, mat.find_solutions_parallel(SolveLimit::All, 4));
+        assert_eq!(None, mat.aborted());
+    }
+
+    #[test]
+    fn test_solutions_iterator() {
+        let mut mat = example_matrix();
+        let mut solutions = mat.solutions();
+        assert_eq!(Some(vec![0, 4, 8]), solutions.next());
+        assert_eq!(Some(vec![0, 7, 5]), solutions.next());
+        drop(solutions);
+
+        // dropping the iterator part way through restores the matrix
+        let solutions: Vec<Vec<usize>> = mat.solutions().collect();
+        assert_eq!(6, solutions.len());
+        assert_eq!(vec![0, 4, 8], solutions[0]);
+    }
+
+    #[test]
+    fn test_deep_search() {
+        // each row covers a single column, so every column adds a level to the search
+        let num_cols = 5_000;
+        let mut mat = DLX::new(num_cols, num_cols);
+        for col in 0..num_cols {
+            mat.add_row(col, &[col]);
+        }
+        assert_eq!(1, mat.count_solutions(None));
+    }
+
+    #[test]
+    fn test_solutions_iterator_no_solution() {
+        let mut mat = DLX::new(2, 2);
+        mat.add_row(0, &[0]);
+        mat.add_row(1, &[0]);
+        assert_eq!(0, mat.into_solutions().count());
+    }
+}
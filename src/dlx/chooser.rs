@@ -0,0 +1,232 @@
+use std::fmt;
+
+use crate::rng::Rng;
+
+// A heuristic for choosing which column to branch on at each node of the search.
+// The choice makes no difference to the solutions found (other than their
+// order), but can make a great difference to the size of the search tree.
+pub trait ColumnChooser: fmt::Debug + Send {
+    // Choose a column from those still to be covered, returning None only if there
+    // are none left. Choosing a column with no choices left ends the branch.
+    fn choose(&mut self, columns: &Columns) -> Option<usize>;
+
+    fn box_clone(&self) -> Box<dyn ColumnChooser>;
+}
+
+impl Clone for Box<dyn ColumnChooser> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+// A view of the primary columns still to be covered at a node of the search. The
+// columns to be covered exactly once are kept in a linked list of the active
+// columns, so only those need to be visited.
+pub struct Columns<'a> {
+    active_next: &'a [usize],
+    bounded_cols: &'a [usize],
+    col_bounds: &'a [(usize, usize)],
+    col_cover_counts: &'a [usize],
+    col_element_counts: &'a [usize]
+}
+
+impl<'a> Columns<'a> {
+    pub(super) fn new(active_next: &'a [usize],
+                      bounded_cols: &'a [usize],
+                      col_bounds: &'a [(usize, usize)],
+                      col_cover_counts: &'a [usize],
+                      col_element_counts: &'a [usize]) -> Self {
+        Self {
+            active_next,
+            bounded_cols,
+            col_bounds,
+            col_cover_counts,
+            col_element_counts
+        }
+    }
+
+    // Iterate over the columns still to be covered, with the number of choices
+    // left of how to cover each one: the number of rows left in it, less any that
+    // must be chosen anyway if it is to be covered more than once, plus one for
+    // covering it no further if it has already been covered enough. A column with
+    // no choices left cannot be covered. The columns come in column order, apart
+    // from those with bounds other than exactly once, which come last.
+    pub fn iter(&self) -> ColumnsIter<'_> {
+        let root = self.active_next.len() - 1;
+        ColumnsIter {
+            columns: self,
+            next_col: self.active_next[root],
+            root,
+            next_bounded: 0
+        }
+    }
+
+    // the number of choices left of how to cover a column (see iter)
+    pub fn choices(&self, col: usize) -> usize {
+        match self.col_bounds[col] {
+            (1, 1) => self.col_element_counts[col],
+            (min_covers, _) => {
+                let needed = min_covers.saturating_sub(self.col_cover_counts[col]);
+                (self.col_element_counts[col] + 1).saturating_sub(needed)
+            }
+        }
+    }
+}
+
+pub struct ColumnsIter<'a> {
+    columns: &'a Columns<'a>,
+    next_col: usize,
+    root: usize,
+    next_bounded: usize
+}
+
+impl Iterator for ColumnsIter<'_> {
+    type Item = (usize, usize);
+
+    #[inline]
+    fn next(&mut self) -> Option<(usize, usize)> {
+        // walk the list of active columns
+        if self.next_col != self.root {
+            let col = self.next_col;
+            self.next_col = self.columns.active_next[col];
+            return Some((col, self.columns.col_element_counts[col]));
+        }
+        self.next_bounded()
+    }
+}
+
+impl ColumnsIter<'_> {
+    // Find the next of any other columns that may still be covered. This is kept
+    // out of line, so that walking the active columns can be inlined into the
+    // choosers' loops, which are the hottest part of the search.
+    #[inline(never)]
+    fn next_bounded(&mut self) -> Option<(usize, usize)> {
+        let columns = self.columns;
+        while let Some(col) = columns.bounded_cols.get(self.next_bounded) {
+            self.next_bounded += 1;
+            if columns.col_cover_counts[*col] < columns.col_bounds[*col].1 {
+                return Some((*col, columns.choices(*col)));
+            }
+        }
+        None
+    }
+}
+
+// Choose the column with the fewest choices (Knuth's "S heuristic"), taking the
+// first in column order if there is a tie. This is the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinRemainingValues;
+
+impl ColumnChooser for MinRemainingValues {
+    fn choose(&mut self, columns: &Columns) -> Option<usize> {
+        let mut min = usize::MAX;
+        let mut min_col = None;
+        for (col, choices) in columns.iter() {
+            if choices < min {
+                min = choices;
+                min_col = Some(col);
+                if min == 0 {
+                    break;
+                }
+            }
+        }
+        min_col
+    }
+
+    fn box_clone(&self) -> Box<dyn ColumnChooser> {
+        Box::new(*self)
+    }
+}
+
+// Choose the first column still to be covered, regardless of its choices.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FirstColumn;
+
+impl ColumnChooser for FirstColumn {
+    fn choose(&mut self, columns: &Columns) -> Option<usize> {
+        columns.iter().next().map(|(col, _)| col)
+    }
+
+    fn box_clone(&self) -> Box<dyn ColumnChooser> {
+        Box::new(*self)
+    }
+}
+
+// As for MinRemainingValues, but ties are broken at random.
+#[derive(Clone, Debug)]
+pub struct RandomMinRemainingValues {
+    rng: Rng
+}
+
+impl RandomMinRemainingValues {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed)
+        }
+    }
+}
+
+impl ColumnChooser for RandomMinRemainingValues {
+    fn choose(&mut self, columns: &Columns) -> Option<usize> {
+        let mut min = usize::MAX;
+        let mut min_col = None;
+        let mut ties = 0;
+        for (col, choices) in columns.iter() {
+            if choices < min {
+                min = choices;
+                min_col = Some(col);
+                ties = 1;
+            } else if choices == min {
+                // keep each of the tied columns with equal probability
+                ties += 1;
+                if self.rng.below(ties) == 0 {
+                    min_col = Some(col);
+                }
+            }
+        }
+        min_col
+    }
+
+    fn box_clone(&self) -> Box<dyn ColumnChooser> {
+        Box::new(self.clone())
+    }
+}
+
+// Choose the column with the fewest choices relative to a priority given to each
+// column, i.e., the column minimising choices / weight, so that columns with
+// higher weights are preferred. Columns without a weight have a weight of 1.
+#[derive(Clone, Debug)]
+pub struct Weighted {
+    weights: Vec<f64>
+}
+
+impl Weighted {
+    pub fn new(weights: Vec<f64>) -> Self {
+        assert!(weights.iter().all(|weight| *weight > 0.0), "Column weights must be positive");
+        Self {
+            weights
+        }
+    }
+}
+
+impl ColumnChooser for Weighted {
+    fn choose(&mut self, columns: &Columns) -> Option<usize> {
+        let mut min = f64::INFINITY;
+        let mut min_col = None;
+        for (col, choices) in columns.iter() {
+            if choices == 0 {
+                return Some(col);
+            }
+            let score = choices as f64 / self.weights.get(col).copied().unwrap_or(1.0);
+            if min_col.is_none() || score < min {
+                min = score;
+                min_col = Some(col);
+            }
+        }
+        min_col
+    }
+
+    fn box_clone(&self) -> Box<dyn ColumnChooser> {
+        Box::new(self.clone())
+    }
+}
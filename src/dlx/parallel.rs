@@ -0,0 +1,266 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use super::{AbortReason, Choice, Search, SharedNodes, SolveLimit, UndoLog, DLX};
+use crate::stats::SearchStats;
+
+// The search tree is split into tasks at the shallowest depth that gives at
+// least this many tasks per thread (up to a maximum depth), so that the work
+// can be spread evenly even though subtrees vary greatly in size.
+const TASKS_PER_THREAD: usize = 8;
+const MAX_SPLIT_DEPTH: usize = 8;
+
+// The outcome of searching the subtree below one task's prefix of rows.
+struct TaskResult {
+    solutions: Vec<Vec<usize>>,
+    count: u64,
+    stats: SearchStats,
+    aborted: Option<AbortReason>
+}
+
+impl TaskResult {
+    fn skipped() -> Self {
+        Self {
+            solutions: Vec::new(),
+            count: 0,
+            stats: SearchStats::default(),
+            aborted: None
+        }
+    }
+}
+
+impl DLX {
+    // As for find_solutions, but the search is split into subtrees that are
+    // explored by a number of threads. Solutions are returned in the same order
+    // as a single-threaded search would find them, and if the search is aborted,
+    // they are the first of those it would have found. That holds for a column
+    // chooser that keeps no state; one that does (such as a random one) starts
+    // each subtree from the same state, so that the order is still the same from
+    // one run to the next with the same number of threads.
+    pub fn find_solutions_parallel(&mut self, limit: SolveLimit, threads: usize) -> Vec<Vec<usize>> {
+        let max_solutions = limit.max_solutions() as u64;
+        let results = self.search_parallel(max_solutions, threads, true);
+
+        let mut solutions = Vec::new();
+        for result in results {
+            solutions.extend(result.solutions);
+        }
+        solutions.truncate(limit.max_solutions());
+        solutions
+    }
+
+    // As for count_solutions, but the search is split across a number of threads.
+    pub fn count_solutions_parallel(&mut self, limit: Option<u64>, threads: usize) -> u64 {
+        let max_solutions = limit.unwrap_or(u64::MAX);
+        let results = self.search_parallel(max_solutions, threads, false);
+
+        let count: u64 = results.iter().map(|result| result.count).sum();
+        count.min(max_solutions)
+    }
+
+    fn search_parallel(&mut self, max_solutions: u64, threads: usize, collect: bool) -> Vec<TaskResult> {
+        let start_time = Instant::now();
+        self.stats = SearchStats::default();
+        self.aborted = None;
+
+        let tasks = self.split_tasks(threads.max(1), start_time);
+        let split_stats = self.stats.clone();
+        let chooser = self.chooser.clone();
+
+        let limits = self.limits.clone();
+        let next_task = AtomicUsize::new(0);
+        let nodes = Arc::new(AtomicU64::new(split_stats.nodes));
+        let stop = AtomicBool::new(false);
+        let results: Mutex<Vec<Option<TaskResult>>> = Mutex::new((0..tasks.len()).map(|_| None).collect());
+
+        thread::scope(|scope| {
+            for _ in 0..threads.max(1).min(tasks.len()) {
+                // each thread works on its own copy of the matrix
+                let mut matrix = self.clone();
+                let chooser = chooser.clone();
+                let (tasks, limits, next_task, nodes, stop, results) =
+                    (&tasks, &limits, &next_task, &nodes, &stop, &results);
+
+                scope.spawn(move || {
+                    let mut undo = UndoLog{vertices: Vec::with_capacity(matrix.num_elements()),
+                                           columns: Vec::with_capacity(matrix.num_cols)};
+                    matrix.shared_nodes = Some(SharedNodes{total: Arc::clone(nodes), counted: 0});
+                    loop {
+                        let index = next_task.fetch_add(1, Ordering::SeqCst);
+                        if index >= tasks.len() || stop.load(Ordering::SeqCst) {
+                            break;
+                        }
+
+                        // skip tasks whose solutions would come after enough earlier ones
+                        if max_solutions != u64::MAX &&
+                           solutions_before(&results.lock().unwrap(), index) >= max_solutions {
+                            results.lock().unwrap()[index] = Some(TaskResult::skipped());
+                            continue;
+                        }
+
+                        // the timeout applies to the search as a whole, so allow only what
+                        // is left of it (the node limit is checked against the shared count)
+                        let mut task_limits = limits.clone();
+                        task_limits.timeout = limits.timeout
+                                                    .map(|timeout| timeout.saturating_sub(start_time.elapsed()));
+                        matrix.set_limits(task_limits);
+
+                        // the choices made in a subtree must not depend on which thread
+                        // searched it, or what else that thread searched before it
+                        matrix.chooser = chooser.clone();
+
+                        let result = matrix.search_task(&tasks[index], max_solutions, collect, &mut undo);
+                        if result.aborted.is_some() {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                        results.lock().unwrap()[index] = Some(result);
+                    }
+                });
+            }
+        });
+
+        // combine the statistics of the tasks with those of splitting the search
+        // (tasks not started because the search was aborted have no results)
+        let results = results.into_inner().unwrap();
+        self.stats = split_stats;
+        for (task, result) in tasks.iter().zip(results.iter()) {
+            if let Some(result) = result {
+                self.stats.merge(&result.stats, task.len());
+                if self.aborted.is_none() {
+                    self.aborted = result.aborted;
+                }
+            }
+        }
+        self.stats.time = start_time.elapsed();
+
+        // tasks after one that did not finish may have found solutions that a
+        // single-threaded search would not have reached, so they are left out
+        let mut finished = Vec::new();
+        for result in results {
+            match result {
+                Some(result) if result.aborted.is_some() => {
+                    finished.push(result);
+                    break;
+                }
+                Some(result) => finished.push(result),
+                None => break
+            }
+        }
+        finished
+    }
+
+    // Find the prefixes of rows chosen down to the depth at which the search
+    // is split, in the order in which a single-threaded search would try them.
+    // There are no tasks if the search is aborted while splitting it.
+    fn split_tasks(&mut self, threads: usize, start_time: Instant) -> Vec<Vec<usize>> {
+        let mut undo = UndoLog{vertices: Vec::new(), columns: Vec::new()};
+        let mut tasks = Vec::new();
+        let stats = self.stats.clone();
+        let chooser = self.chooser.clone();
+        for depth in 1..=MAX_SPLIT_DEPTH {
+            self.stats = stats.clone();
+            self.chooser = chooser.clone();
+            self.next_time_check = 0;
+            tasks.clear();
+            self.split(depth, &mut Vec::new(), &mut tasks, &mut undo, start_time);
+            if self.aborted.is_some() {
+                tasks.clear();
+                break;
+            }
+            if tasks.len() >= threads * TASKS_PER_THREAD {
+                break;
+            }
+        }
+        tasks
+    }
+
+    fn split(&mut self, depth: usize, prefix: &mut Vec<usize>, tasks: &mut Vec<Vec<usize>>, undo: &mut UndoLog,
+             start_time: Instant) {
+        if prefix.len() == depth {
+            tasks.push(prefix.clone());
+            return;
+        }
+
+        // give up if any of the limits on the search have been reached
+        if let Some(reason) = self.limit_reached(start_time) {
+            self.aborted = Some(reason);
+            return;
+        }
+
+        match self.choose_column() {
+            // the search excludes rows from a column that may be covered other than
+            // exactly once as it goes, which a prefix of rows cannot capture
+            Choice::Column(col) if self.col_bounds[col] != (1, 1) => tasks.push(prefix.clone()),
+            Choice::Column(col) => {
+                self.stats.record_node(prefix.len(), self.col_element_counts[col]);
+
+                let col_id = self.col_vertex_id(col);
+                let mut elem_id = self.vertices[col_id].bottom_id;
+                while elem_id != col_id {
+                    let row = self.vertices[elem_id].row;
+                    let (vertices_mark, columns_mark) = (undo.vertices.len(), undo.columns.len());
+                    self.eliminate_row_into(row, undo);
+                    prefix.push(row);
+
+                    self.split(depth, prefix, tasks, undo, start_time);
+
+                    prefix.pop();
+                    self.restore(undo, vertices_mark, columns_mark);
+                    if self.aborted.is_some() {
+                        return;
+                    }
+                    elem_id = self.vertices[elem_id].bottom_id;
+                }
+            }
+            Choice::DeadEnd => self.stats.record_node(prefix.len(), 0),
+            // the search finished early along this branch, so the prefix is a solution
+            Choice::Solved => tasks.push(prefix.clone())
+        }
+    }
+
+    // Search the subtree below a prefix of rows, leaving the matrix as it was found.
+    fn search_task(&mut self, prefix: &[usize], max_solutions: u64, collect: bool, undo: &mut UndoLog) -> TaskResult {
+        for row in prefix {
+            self.eliminate_row_into(*row, undo);
+        }
+
+        let mut solutions = Vec::new();
+        let mut count = 0;
+        let mut search = Search::new(self);
+        while count < max_solutions && self.next_solution(&mut search) {
+            count += 1;
+            if collect {
+                let mut solution = prefix.to_vec();
+                solution.extend_from_slice(&search.candidate);
+                solutions.push(solution);
+            }
+        }
+        self.abandon(&mut search);
+        self.restore(undo, 0, 0);
+
+        // add any nodes not yet counted to the count shared with the other tasks
+        self.nodes_searched();
+
+        TaskResult {
+            solutions,
+            count,
+            stats: self.stats.clone(),
+            aborted: self.aborted
+        }
+    }
+}
+
+// the number of solutions found by the tasks before a given task, as long as
+// they have all finished (otherwise, there is no telling yet)
+fn solutions_before(results: &[Option<TaskResult>], index: usize) -> u64 {
+    let mut count = 0;
+    for result in &results[..index] {
+        match result {
+            Some(result) => count += result.count,
+            None => return 0
+        }
+    }
+    count
+}
@@ -0,0 +1,64 @@
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SudokuError {
+    // the dimension is not a supported Sudoku size
+    InvalidDimension(usize),
+    // blocks of this shape do not make a Sudoku of a supported size
+    InvalidBlockShape { height: usize, width: usize },
+    // the input has more entries than the largest supported Sudoku
+    TooManyEntries { found: usize, max: usize },
+    // a cell value is zero or larger than the dimension of the Sudoku
+    ValueOutOfRange { row: usize, col: usize, value: usize, max: usize },
+    // the number of entries cannot be arranged into a square grid
+    NonSquareEntryCount(usize),
+    // the number of entries, or of labels in a layout of regions, does not fill
+    // the Sudoku
+    WrongEntryCount { found: usize, expected: usize },
+    // a region (numbered from 0) of a jigsaw Sudoku does not have as many cells
+    // as the dimension
+    WrongRegionSize { region: usize, size: usize, expected: usize },
+    // the cells of a region of a jigsaw Sudoku are not all joined together
+    DisconnectedRegion(usize),
+    // a token in the input (at 1-based line and column) is not a valid number,
+    // nor a character used to draw the grid
+    UnparsableToken { token: String, line: usize, column: usize },
+    // a line (1-based) of a candidate grid does not have the expected layout
+    MalformedCandidateGrid { line: usize },
+    // a generated puzzle could not be left with as few clues as asked for
+    // without losing the uniqueness of its solution
+    ClueTargetNotReached { target: usize, clues: usize }
+}
+
+impl fmt::Display for SudokuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SudokuError::InvalidDimension(dimension) =>
+                write!(f, "Illegal Sudoku dimension: {}", dimension),
+            SudokuError::InvalidBlockShape { height, width } =>
+                write!(f, "Illegal block shape: {}x{}", height, width),
+            SudokuError::TooManyEntries { found, max } =>
+                write!(f, "Too many entries: found {}, at most {} are supported", found, max),
+            SudokuError::ValueOutOfRange { row, col, value, max } =>
+                write!(f, "Value {} at row {}, col {} is out of range (1..={})",
+                       value, row + 1, col + 1, max),
+            SudokuError::NonSquareEntryCount(count) =>
+                write!(f, "Found {} entries, which cannot form a square grid", count),
+            SudokuError::WrongEntryCount { found, expected } =>
+                write!(f, "Found {} entries, but expected {}", found, expected),
+            SudokuError::WrongRegionSize { region, size, expected } =>
+                write!(f, "Region {} has {} cells, but expected {}", region + 1, size, expected),
+            SudokuError::DisconnectedRegion(region) =>
+                write!(f, "Region {} is not connected", region + 1),
+            SudokuError::UnparsableToken { token, line, column } =>
+                write!(f, "Could not parse '{}' at line {}, column {}", token, line, column),
+            SudokuError::MalformedCandidateGrid { line } =>
+                write!(f, "Malformed candidate grid at line {}", line),
+            SudokuError::ClueTargetNotReached { target, clues } =>
+                write!(f, "Could only remove clues down to {}, short of the target of {}", clues, target)
+        }
+    }
+}
+
+impl error::Error for SudokuError {}
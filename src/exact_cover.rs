@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::hash::Hash;
+
+use super::dlx::{DLX, SolveLimit};
+
+// A general exact cover problem, in which the rows and columns are labelled with
+// values of the user's choosing rather than numbered, e.g., rows may be labelled
+// with the placements of pieces in a puzzle and columns with the cells and pieces
+// they cover. A solution is a set of rows that covers every primary column
+// exactly once and every secondary column at most once (unless the columns are
+// given other bounds, or are coloured), and is given as the labels of those rows.
+pub struct ExactCover<R, C> {
+    rows: Vec<R>,
+    row_cols: Vec<Vec<(usize, Option<usize>)>>,
+    cols: Vec<C>,
+    secondary_cols: Vec<bool>,
+    coloured_cols: Vec<bool>,
+    col_bounds: Vec<Option<(usize, usize)>>,
+    col_ids: HashMap<C, usize>
+}
+
+// Why a column cannot be given the bounds, colours or kind asked for. Columns
+// are given by index, which for a column not yet added is the one it would have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExactCoverError {
+    // the minimum is larger than the maximum, or the maximum is zero
+    InvalidBounds { col: usize, min: usize, max: usize },
+    // a secondary column (which every coloured column is) need not be covered,
+    // so its minimum must be zero
+    SecondaryColumnMinimum { col: usize, min: usize },
+    // a coloured column cannot be covered more than once
+    ColouredColumnMaximum { col: usize, max: usize }
+}
+
+impl fmt::Display for ExactCoverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExactCoverError::InvalidBounds { col, min, max } =>
+                write!(f, "Invalid bounds [{}, {}] for column {}", min, max, col),
+            ExactCoverError::SecondaryColumnMinimum { col, min } =>
+                write!(f, "Secondary column {} cannot have a minimum of {}", col, min),
+            ExactCoverError::ColouredColumnMaximum { col, max } =>
+                write!(f, "Coloured column {} cannot have a maximum of {}", col, max)
+        }
+    }
+}
+
+impl error::Error for ExactCoverError {}
+
+impl<R: Clone, C: Clone + Eq + Hash> ExactCover<R, C> {
+    pub fn new() -> Self {
+        Self {
+            rows: Vec::new(),
+            row_cols: Vec::new(),
+            cols: Vec::new(),
+            secondary_cols: Vec::new(),
+            coloured_cols: Vec::new(),
+            col_bounds: Vec::new(),
+            col_ids: HashMap::new()
+        }
+    }
+
+    // Add a primary column, which must be covered, returning its index. Columns
+    // are also added as primary columns as they are mentioned by rows, but columns
+    // that no row covers must be added explicitly (making the problem unsolvable).
+    // Adding a column more than once has no effect.
+    pub fn add_column(&mut self, label: C) -> usize {
+        if let Some(id) = self.col_ids.get(&label) {
+            return *id;
+        }
+        let id = self.cols.len();
+        self.cols.push(label.clone());
+        self.secondary_cols.push(false);
+        self.coloured_cols.push(false);
+        self.col_bounds.push(None);
+        self.col_ids.insert(label, id);
+        id
+    }
+
+    // Add a secondary column, which may be covered at most once but need not be
+    // covered at all, returning its index. A column already added is made
+    // secondary, unless it has bounds that say it must be covered.
+    pub fn add_secondary_column(&mut self, label: C) -> Result<usize, ExactCoverError> {
+        self.check_secondary(&label)?;
+        let id = self.add_column(label);
+        self.secondary_cols[id] = true;
+        Ok(id)
+    }
+
+    pub fn is_secondary_column(&self, col: usize) -> bool {
+        self.secondary_cols[col]
+    }
+
+    // Set the minimum and maximum number of times a column may be covered (see
+    // DLX::set_column_bounds), adding it as a primary column if necessary. The
+    // minimum of a secondary column must be zero, and a coloured column cannot
+    // be covered more than once.
+    pub fn set_column_bounds(&mut self, label: C, min: usize, max: usize) -> Result<(), ExactCoverError> {
+        let col = self.col_index(&label);
+        if min > max || max == 0 {
+            return Err(ExactCoverError::InvalidBounds { col, min, max });
+        }
+        if min > 0 && self.secondary_cols.get(col) == Some(&true) {
+            return Err(ExactCoverError::SecondaryColumnMinimum { col, min });
+        }
+        if max > 1 && self.coloured_cols.get(col) == Some(&true) {
+            return Err(ExactCoverError::ColouredColumnMaximum { col, max });
+        }
+        let id = self.add_column(label);
+        self.col_bounds[id] = Some((min, max));
+        Ok(())
+    }
+
+    // Add a row, given the labels of the columns it covers.
+    pub fn add_row<I: IntoIterator<Item = C>>(&mut self, label: R, cols: I) {
+        let row_cols = cols.into_iter().map(|col| (self.add_column(col), None)).collect();
+        self.rows.push(label);
+        self.row_cols.push(row_cols);
+    }
+
+    // Add a row that also covers secondary columns with colours: rows may share
+    // such a column as long as they give it the same colour. The coloured columns
+    // are made secondary if they are not already, so they cannot have bounds
+    // that say they must be covered, or that they may be covered more than once.
+    // Nothing is added if any of them does.
+    pub fn add_coloured_row<I, J>(&mut self, label: R, cols: I, coloured_cols: J) -> Result<(), ExactCoverError>
+        where I: IntoIterator<Item = C>,
+              J: IntoIterator<Item = (C, usize)>
+    {
+        let coloured_cols: Vec<(C, usize)> = coloured_cols.into_iter().collect();
+        for (col, _) in &coloured_cols {
+            self.check_secondary(col)?;
+            if let Some((_, max)) = self.bounds(col).filter(|(_, max)| *max > 1) {
+                return Err(ExactCoverError::ColouredColumnMaximum { col: self.col_index(col), max });
+            }
+        }
+
+        self.add_row(label, cols);
+        for (col, colour) in coloured_cols {
+            let id = self.add_column(col);
+            self.secondary_cols[id] = true;
+            self.coloured_cols[id] = true;
+            if let Some(row_cols) = self.row_cols.last_mut() {
+                row_cols.push((id, Some(colour)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.cols.len()
+    }
+
+    pub fn rows(&self) -> &[R] {
+        &self.rows
+    }
+
+    pub fn cols(&self) -> &[C] {
+        &self.cols
+    }
+
+    // Lazily iterate over the solutions, each given as the labels of its rows.
+    pub fn solutions(&self) -> impl Iterator<Item = Vec<R>> + '_ {
+        self.build_matrix()
+            .into_solutions()
+            .map(move |solution| self.row_labels(&solution))
+    }
+
+    pub fn solve(&self, limit: SolveLimit) -> Vec<Vec<R>> {
+        self.solutions().take(limit.max_solutions()).collect()
+    }
+
+    pub fn count_solutions(&self, limit: Option<u64>) -> u64 {
+        self.build_matrix().count_solutions(limit)
+    }
+
+    fn build_matrix(&self) -> DLX {
+        // the matrix needs the primary columns first, so number them separately
+        let num_secondary_cols = self.secondary_cols.iter().filter(|secondary| **secondary).count();
+        let num_primary_cols = self.cols.len() - num_secondary_cols;
+        let mut next_ids = (0, num_primary_cols);
+        let matrix_cols: Vec<usize> = self.secondary_cols.iter().map(|secondary| {
+            let next_id = if *secondary { &mut next_ids.1 } else { &mut next_ids.0 };
+            *next_id += 1;
+            *next_id - 1
+        }).collect();
+
+        let mut mat = DLX::new_with_secondary(self.rows.len(), num_primary_cols, num_secondary_cols);
+        for (col, bounds) in self.col_bounds.iter().enumerate() {
+            if let Some((min, max)) = bounds {
+                mat.set_column_bounds(matrix_cols[col], *min, *max);
+            }
+        }
+        for (row, cols) in self.row_cols.iter().enumerate() {
+            for (col, colour) in cols {
+                match colour {
+                    Some(colour) => mat.set_coloured_element(row, matrix_cols[*col], *colour),
+                    None => mat.set_element(row, matrix_cols[*col])
+                }
+            }
+        }
+        mat
+    }
+
+    // the index of a column, or the index it would be given if it were added
+    fn col_index(&self, label: &C) -> usize {
+        self.col_ids.get(label).copied().unwrap_or(self.cols.len())
+    }
+
+    fn bounds(&self, label: &C) -> Option<(usize, usize)> {
+        self.col_ids.get(label).and_then(|id| self.col_bounds[*id])
+    }
+
+    // check that a column can be made secondary, i.e., it need not be covered
+    fn check_secondary(&self, label: &C) -> Result<(), ExactCoverError> {
+        match self.bounds(label) {
+            Some((min, _)) if min > 0 => Err(ExactCoverError::SecondaryColumnMinimum { col: self.col_index(label), min }),
+            _ => Ok(())
+        }
+    }
+
+    fn row_labels(&self, solution: &[usize]) -> Vec<R> {
+        solution.iter().map(|row| self.rows[*row].clone()).collect()
+    }
+}
+
+impl<R: Clone, C: Clone + Eq + Hash> Default for ExactCover<R, C> {
+    fn default() -> Self {
+        ExactCover::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExactCover, ExactCoverError};
+    use super::SolveLimit;
+
+    // Knuth's example from the Dancing Links paper
+    fn example_problem() -> ExactCover<&'static str, char> {
+        let mut problem = ExactCover::new();
+        problem.add_row("CEF", vec!['C', 'E', 'F']);
+        problem.add_row("ADG", vec!['A', 'D', 'G']);
+        problem.add_row("BCF", vec!['B', 'C', 'F']);
+        problem.add_row("AD", vec!['A', 'D']);
+        problem.add_row("BG", vec!['B', 'G']);
+        problem.add_row("DEG", vec!['D', 'E', 'G']);
+        problem
+    }
+
+    #[test]
+    fn test_add_column() {
+        let mut problem = example_problem();
+        assert_eq!(6, problem.num_rows());
+        assert_eq!(7, problem.num_cols());
+        assert_eq!(0, problem.add_column('C'));
+        assert_eq!(7, problem.add_column('H'));
+        assert_eq!(8, problem.num_cols());
+    }
+
+    #[test]
+    fn test_solve() {
+        let problem = example_problem();
+        let mut solutions = problem.solve(SolveLimit::All);
+        assert_eq!(1, solutions.len());
+
+        solutions[0].sort();
+        assert_eq!(vec!["AD", "BG", "CEF"], solutions[0]);
+        assert_eq!(1, problem.count_solutions(None));
+    }
+
+    #[test]
+    fn test_secondary_column() {
+        // the secondary column need not be covered, but cannot be covered twice
+        let mut problem = ExactCover::new();
+        assert_eq!(Ok(0), problem.add_secondary_column('X'));
+        problem.add_row("AX", vec!['A', 'X']);
+        problem.add_row("BX", vec!['B', 'X']);
+        problem.add_row("B", vec!['B']);
+        assert!(problem.is_secondary_column(0));
+        assert_eq!(vec![vec!["AX", "B"]], problem.solve(SolveLimit::All));
+    }
+
+    #[test]
+    fn test_colours_and_bounds() {
+        // two workers each take one or two of three shifts, and the early and late
+        // shifts need the car, which can only be used by one of them
+        let mut problem = ExactCover::new();
+        for (colour, worker) in ["Ann", "Bob"].iter().enumerate() {
+            problem.set_column_bounds(*worker, 1, 2).unwrap();
+            problem.add_coloured_row((*worker, "early"), vec![*worker, "early"], vec![("car", colour)]).unwrap();
+            problem.add_coloured_row((*worker, "late"), vec![*worker, "late"], vec![("car", colour)]).unwrap();
+            problem.add_row((*worker, "night"), vec![*worker, "night"]);
+        }
+        let car = problem.add_column("car");
+        assert!(problem.is_secondary_column(car));
+
+        let mut solutions = problem.solve(SolveLimit::All);
+        for solution in solutions.iter_mut() {
+            solution.sort();
+        }
+        solutions.sort();
+        assert_eq!(vec![vec![("Ann", "early"), ("Ann", "late"), ("Bob", "night")],
+                        vec![("Ann", "night"), ("Bob", "early"), ("Bob", "late")]],
+                   solutions);
+    }
+
+    #[test]
+    fn test_column_errors() {
+        let mut problem = ExactCover::new();
+        problem.add_coloured_row("AX", vec!['A'], vec![('X', 0)]).unwrap();
+        problem.set_column_bounds('B', 1, 2).unwrap();
+        assert_eq!(Err(ExactCoverError::InvalidBounds { col: 3, min: 2, max: 1 }),
+                   problem.set_column_bounds('C', 2, 1));
+        assert_eq!(Err(ExactCoverError::InvalidBounds { col: 0, min: 0, max: 0 }),
+                   problem.set_column_bounds('A', 0, 0));
+
+        // a coloured column is secondary, and cannot be covered more than once
+        assert_eq!(Err(ExactCoverError::SecondaryColumnMinimum { col: 1, min: 1 }),
+                   problem.set_column_bounds('X', 1, 1));
+        assert_eq!(Err(ExactCoverError::ColouredColumnMaximum { col: 1, max: 2 }),
+                   problem.set_column_bounds('X', 0, 2));
+        assert_eq!(Ok(()), problem.set_column_bounds('X', 0, 1));
+
+        // nor can a column that must be covered, or may be covered more than once, be coloured
+        assert_eq!(Err(ExactCoverError::SecondaryColumnMinimum { col: 2, min: 1 }),
+                   problem.add_coloured_row("AB", vec!['A'], vec![('B', 0)]));
+        assert_eq!(Err(ExactCoverError::SecondaryColumnMinimum { col: 2, min: 1 }),
+                   problem.add_secondary_column('B'));
+        problem.set_column_bounds('Y', 0, 2).unwrap();
+        assert_eq!(Err(ExactCoverError::ColouredColumnMaximum { col: 3, max: 2 }),
+                   problem.add_coloured_row("AY", vec!['A'], vec![('Y', 0)]));
+
+        // nothing is added when there is an error
+        assert_eq!(1, problem.num_rows());
+        assert_eq!(4, problem.num_cols());
+        assert_eq!(0, problem.count_solutions(None));
+    }
+
+    #[test]
+    fn test_uncoverable_column() {
+        let mut problem = example_problem();
+        problem.add_column('H');
+        assert_eq!(0, problem.count_solutions(None));
+    }
+}
@@ -0,0 +1,228 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use super::rng::Rng;
+use super::solver;
+use super::solver::SolveOptions;
+use super::{Sudoku, SudokuError, Variant};
+
+// The symmetry of the pattern of clues in a generated puzzle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    // unchanged by rotating the grid through 180 degrees
+    Rotational,
+    // unchanged by reflecting the grid in its leading diagonal
+    Diagonal,
+    // unchanged by reflecting the grid from left to right
+    Mirror
+}
+
+impl Symmetry {
+    // the cells that must be given or removed together with a cell, in order
+    fn orbit(self, dimension: usize, row: usize, col: usize) -> Vec<(usize, usize)> {
+        let image = match self {
+            Symmetry::None => (row, col),
+            Symmetry::Rotational => (dimension - 1 - row, dimension - 1 - col),
+            Symmetry::Diagonal => (col, row),
+            Symmetry::Mirror => (row, dimension - 1 - col)
+        };
+        let mut orbit = vec![(row, col), image];
+        orbit.sort_unstable();
+        orbit.dedup();
+        orbit
+    }
+}
+
+// the error given when a symmetry cannot be parsed from its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSymmetryError(String);
+
+impl fmt::Display for ParseSymmetryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown symmetry: {} (expected none, rot180, diagonal or mirror)", self.0)
+    }
+}
+
+impl error::Error for ParseSymmetryError {}
+
+impl FromStr for Symmetry {
+    type Err = ParseSymmetryError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "none" => Ok(Symmetry::None),
+            "rot180" => Ok(Symmetry::Rotational),
+            "diagonal" => Ok(Symmetry::Diagonal),
+            "mirror" => Ok(Symmetry::Mirror),
+            _ => Err(ParseSymmetryError(str.to_string()))
+        }
+    }
+}
+
+// Options for generating a puzzle. Clues are removed until the target number is
+// reached, or no more can be removed without losing the uniqueness of the
+// solution (with no target, as many as possible are removed). The same options
+// always generate the same puzzle.
+//
+// Checking the uniqueness of the solution can take a long time for the largest
+// puzzles, so it may be limited to a number of nodes of the search per cell of
+// the puzzle. A clue whose check goes past the limit is kept, even though it
+// might have been removed.
+#[derive(Clone, Debug)]
+pub struct GeneratorOptions {
+    pub size: usize,
+    pub clues: Option<usize>,
+    pub symmetry: Symmetry,
+    pub variant: Variant,
+    pub seed: u64,
+    pub max_nodes_per_cell: Option<u64>
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            size: 9,
+            clues: None,
+            symmetry: Symmetry::None,
+            variant: Variant::Standard,
+            seed: 0,
+            max_nodes_per_cell: None
+        }
+    }
+}
+
+// Generate a puzzle with a unique solution: a completed grid is chosen at random,
+// then clues are removed from it in a random order, as long as the puzzle still
+// has a unique solution. It is an error if the target number of clues cannot be
+// reached that way.
+pub fn generate(options: &GeneratorOptions) -> Result<Sudoku, SudokuError> {
+    let mut empty = Sudoku::try_new(options.size)?;
+    empty.set_variant(options.variant);
+    let mut rng = Rng::new(options.seed);
+    let solution = match solver::random_solution(&empty, rng.next_u64()) {
+        Some(solution) => solution,
+        None => unreachable!("An empty Sudoku always has a solution")
+    };
+
+    // find the groups of cells to remove together, each represented by its first cell
+    let dimension = options.size;
+    let mut orbits = Vec::new();
+    for row in 0..dimension {
+        for col in 0..dimension {
+            let orbit = options.symmetry.orbit(dimension, row, col);
+            if orbit[0] == (row, col) {
+                orbits.push(orbit);
+            }
+        }
+    }
+    rng.shuffle(&mut orbits);
+
+    let check_options = SolveOptions {
+        max_nodes: options.max_nodes_per_cell.map(|nodes| nodes * empty.num_cells() as u64),
+        ..Default::default()
+    };
+    let target = options.clues.unwrap_or(0);
+    let mut puzzle = solution.clone();
+    let mut num_clues = puzzle.num_cells();
+    for orbit in orbits {
+        if num_clues <= target {
+            break;
+        }
+        if num_clues < target + orbit.len() {
+            continue;
+        }
+
+        for (row, col) in &orbit {
+            puzzle.clear_cell(*row, *col);
+        }
+        let outcome = solver::count_solutions_with(&puzzle, Some(2), &check_options);
+        if outcome.count == 1 && outcome.aborted.is_none() {
+            num_clues -= orbit.len();
+        } else {
+            for (row, col) in &orbit {
+                if let Some(val) = solution.cell_value(*row, *col) {
+                    puzzle.set_cell_value(*row, *col, val);
+                }
+            }
+        }
+    }
+    if num_clues > target && options.clues.is_some() {
+        return Err(SudokuError::ClueTargetNotReached { target, clues: num_clues });
+    }
+    Ok(puzzle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, GeneratorOptions, ParseSymmetryError, Symmetry};
+    use super::super::solver;
+    use super::super::{Sudoku, SudokuError, Variant};
+
+    fn is_symmetric(sudoku: &Sudoku, symmetry: Symmetry) -> bool {
+        let dimension = sudoku.dimension();
+        (0..dimension).all(|row| (0..dimension).all(|col| {
+            let given = sudoku.cell_value(row, col).is_some();
+            symmetry.orbit(dimension, row, col)
+                    .iter()
+                    .all(|(row, col)| sudoku.cell_value(*row, *col).is_some() == given)
+        }))
+    }
+
+    #[test]
+    fn test_symmetry_from_str() {
+        assert_eq!(Ok(Symmetry::Rotational), "rot180".parse());
+        assert_eq!(Ok(Symmetry::Mirror), "mirror".parse());
+        assert_eq!(Err(ParseSymmetryError("rot90".to_string())), "rot90".parse::<Symmetry>());
+    }
+
+    #[test]
+    fn test_generate() {
+        for symmetry in &[Symmetry::None, Symmetry::Rotational, Symmetry::Diagonal, Symmetry::Mirror] {
+            let options = GeneratorOptions { symmetry: *symmetry, seed: 42, ..Default::default() };
+            let puzzle = generate(&options).unwrap();
+            assert_eq!(9, puzzle.dimension());
+            assert!(puzzle.num_completed_cells() < 40);
+            assert!(is_symmetric(&puzzle, *symmetry));
+            assert!(matches!(solver::uniqueness(&puzzle), solver::Uniqueness::Unique(_)));
+            assert_eq!(puzzle, generate(&options).unwrap());
+        }
+
+        let options = GeneratorOptions { seed: 43, ..Default::default() };
+        assert_ne!(generate(&GeneratorOptions { seed: 42, ..Default::default() }).unwrap(),
+                   generate(&options).unwrap());
+    }
+
+    #[test]
+    fn test_generate_variant() {
+        let options = GeneratorOptions { variant: Variant::X, seed: 1, ..Default::default() };
+        let puzzle = generate(&options).unwrap();
+        assert_eq!(Variant::X, puzzle.variant());
+        match solver::uniqueness(&puzzle) {
+            solver::Uniqueness::Unique(solution) => assert!(solution.is_consistent()),
+            _ => panic!("a Sudoku-X should have a unique solution")
+        }
+    }
+
+    #[test]
+    fn test_generate_clues() {
+        let options = GeneratorOptions { size: 4, clues: Some(8), seed: 1, ..Default::default() };
+        let puzzle = generate(&options).unwrap();
+        assert_eq!(8, puzzle.num_completed_cells());
+        assert!(matches!(solver::uniqueness(&puzzle), solver::Uniqueness::Unique(_)));
+
+        let options = GeneratorOptions { size: 16, clues: Some(200), seed: 1, ..Default::default() };
+        assert_eq!(200, generate(&options).unwrap().num_completed_cells());
+
+        // a 9x9 puzzle needs at least 17 clues, and limiting the checks keeps even more
+        let options = GeneratorOptions { clues: Some(16), seed: 1, ..Default::default() };
+        assert!(matches!(generate(&options),
+                         Err(SudokuError::ClueTargetNotReached { target: 16, clues }) if clues >= 17));
+        let options = GeneratorOptions { clues: Some(30), max_nodes_per_cell: Some(0), seed: 1, ..Default::default() };
+        assert_eq!(Err(SudokuError::ClueTargetNotReached { target: 30, clues: 81 }), generate(&options));
+
+        let options = GeneratorOptions { size: 7, ..Default::default() };
+        assert_eq!(SudokuError::InvalidDimension(7), generate(&options).unwrap_err());
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+
+use super::logic::{LogicSolver, Step, Technique};
+use super::solver;
+use super::solver::{AbortReason, SearchStats, SolveOptions};
+use super::Sudoku;
+
+// How difficult a puzzle is for a person to solve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+    // logic alone is not enough, and guessing is required
+    Diabolical
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::Easy => "Easy",
+            Level::Medium => "Medium",
+            Level::Hard => "Hard",
+            Level::Expert => "Expert",
+            Level::Diabolical => "Diabolical"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// The search that finished off a puzzle once logic had stalled: its statistics,
+// the number of solutions found (up to 2, as the search checks whether the
+// solution is unique), and the reason it gave up, if it did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Guessing {
+    pub stats: SearchStats,
+    pub solutions: u64,
+    pub aborted: Option<AbortReason>
+}
+
+// The difficulty of a puzzle, worked out from the steps taken to solve it with
+// logic. The score adds up the difficulty of each step, so longer solutions score
+// higher than shorter ones using the same techniques.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grade {
+    pub level: Level,
+    pub score: u64,
+    pub hardest_technique: Option<Technique>,
+    pub steps: Vec<Step>,
+    pub guessing: Option<Guessing>
+}
+
+impl Grade {
+    pub fn requires_guessing(&self) -> bool {
+        self.guessing.is_some()
+    }
+}
+
+// the score for having to guess, to which is added a score for each backtrack
+const GUESSING_SCORE: u64 = 100;
+const BACKTRACK_SCORE: u64 = 10;
+
+fn technique_level(technique: Technique) -> Level {
+    match technique {
+        Technique::NakedSingle | Technique::HiddenSingle => Level::Easy,
+        Technique::NakedPair | Technique::HiddenPair |
+        Technique::PointingPair | Technique::BoxLineReduction => Level::Medium,
+        Technique::NakedTriple | Technique::HiddenTriple |
+        Technique::NakedQuad | Technique::HiddenQuad | Technique::XWing => Level::Hard,
+        Technique::Swordfish | Technique::Jellyfish | Technique::XYWing |
+        Technique::XYZWing | Technique::SimpleColouring => Level::Expert
+    }
+}
+
+fn technique_score(technique: Technique) -> u64 {
+    match technique {
+        Technique::NakedSingle => 1,
+        Technique::HiddenSingle => 2,
+        Technique::NakedPair | Technique::PointingPair | Technique::BoxLineReduction => 5,
+        Technique::HiddenPair => 7,
+        Technique::NakedTriple => 10,
+        Technique::HiddenTriple => 12,
+        Technique::NakedQuad => 15,
+        Technique::HiddenQuad => 18,
+        Technique::XWing => 20,
+        Technique::Swordfish | Technique::XYWing => 30,
+        Technique::XYZWing | Technique::SimpleColouring => 35,
+        Technique::Jellyfish => 40
+    }
+}
+
+pub fn grade(sudoku: &Sudoku) -> Grade {
+    grade_with(sudoku, &SolveOptions::default())
+}
+
+// as for grade, but limiting any search needed once logic has stalled according
+// to the options (the solution limit is not applied)
+pub fn grade_with(sudoku: &Sudoku, options: &SolveOptions) -> Grade {
+    let mut solver = LogicSolver::new(sudoku);
+    let steps: Vec<Step> = solver.by_ref().collect();
+
+    let mut score = steps.iter().map(|step| technique_score(step.technique)).sum();
+    let hardest_technique = steps.iter()
+                                 .map(|step| step.technique)
+                                 .max_by_key(|technique| (technique_level(*technique), *technique));
+    let mut level = hardest_technique.map_or(Level::Easy, technique_level);
+
+    let guessing = if solver.is_solved() {
+        None
+    } else {
+        let outcome = solver::count_solutions_with(&solver.sudoku(), Some(2), options);
+        level = Level::Diabolical;
+        score += GUESSING_SCORE + BACKTRACK_SCORE * outcome.stats.backtracks;
+        Some(Guessing {
+            stats: outcome.stats,
+            solutions: outcome.count,
+            aborted: outcome.aborted
+        })
+    };
+
+    Grade {
+        level,
+        score,
+        hardest_technique,
+        steps,
+        guessing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grade, grade_with, Level};
+    use super::super::logic::Technique;
+    use super::super::solver::{AbortReason, SolveOptions};
+    use super::super::sudoku::parse_line;
+    use super::super::Sudoku;
+
+    #[test]
+    fn test_grade() {
+        let easy = grade(&parse_line("3.5...9..4..9..7.29.6..1....1.45..89.4.1.2.7.75..83.2....2..3.61.2..6..7..4...2.1"));
+        assert_eq!(Level::Easy, easy.level);
+        assert_eq!(Some(Technique::NakedSingle), easy.hardest_technique);
+        assert_eq!(47, easy.score);
+        assert!(!easy.requires_guessing());
+
+        let expert = grade(&parse_line(".4.....8.8..3.2..5..7.1.6...6.....3...5.7.1...8.....5...1.4.5..3..6.9..4.2.....9."));
+        assert_eq!(Level::Expert, expert.level);
+        assert_eq!(Some(Technique::Swordfish), expert.hardest_technique);
+        assert!(expert.score > easy.score);
+    }
+
+    #[test]
+    fn test_grade_guessing() {
+        let sudoku = parse_line("..67..4......5..7..7.1...3.8...79.16.6.3.175.7..62...469...7.23.3796..4...8...967");
+        let diabolical = grade(&sudoku);
+        assert_eq!(Level::Diabolical, diabolical.level);
+        let guessing = diabolical.guessing.unwrap();
+        assert_eq!(1, guessing.solutions);
+        assert_eq!(None, guessing.aborted);
+        assert!(guessing.stats.nodes > 0);
+
+        let empty = grade(&Sudoku::new(4));
+        assert!(empty.steps.is_empty());
+        assert_eq!(None, empty.hardest_technique);
+        assert_eq!(2, empty.guessing.unwrap().solutions);
+
+        let options = SolveOptions { max_nodes: Some(1), ..Default::default() };
+        let aborted = grade_with(&sudoku, &options);
+        assert_eq!(Some(AbortReason::NodeLimit), aborted.guessing.unwrap().aborted);
+    }
+}
@@ -0,0 +1,21 @@
+//! A Sudoku solver that represents puzzles as exact cover problems and solves
+//! them using Donald Knuth's Algorithm X and a version of the Dancing Links
+//! technique.
+
+pub mod analysis;
+mod candidates;
+pub mod dlx;
+mod error;
+pub mod exact_cover;
+pub mod generator;
+pub mod grading;
+pub mod logic;
+pub mod matrix;
+pub mod rng;
+pub mod solver;
+pub mod stats;
+mod sudoku;
+
+pub use crate::candidates::CandidateGrid;
+pub use crate::error::SudokuError;
+pub use crate::sudoku::{ParseVariantError, Sudoku, Variant};
@@ -0,0 +1,475 @@
+use std::fmt;
+
+use super::{CandidateGrid, Sudoku};
+
+mod hint;
+mod techniques;
+
+pub use self::hint::{hint, hint_for_grid, Contradiction, Hint};
+
+// The techniques used by the logical solver, in order of difficulty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Technique {
+    NakedSingle,
+    HiddenSingle,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    HiddenTriple,
+    NakedQuad,
+    HiddenQuad,
+    PointingPair,
+    BoxLineReduction,
+    XWing,
+    Swordfish,
+    Jellyfish,
+    XYWing,
+    XYZWing,
+    SimpleColouring
+}
+
+impl Technique {
+    // all of the techniques, easiest first
+    pub const ALL: [Technique; 16] = [
+        Technique::NakedSingle,
+        Technique::HiddenSingle,
+        Technique::NakedPair,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::HiddenTriple,
+        Technique::NakedQuad,
+        Technique::HiddenQuad,
+        Technique::PointingPair,
+        Technique::BoxLineReduction,
+        Technique::XWing,
+        Technique::Swordfish,
+        Technique::Jellyfish,
+        Technique::XYWing,
+        Technique::XYZWing,
+        Technique::SimpleColouring
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Technique::NakedSingle => "Naked single",
+            Technique::HiddenSingle => "Hidden single",
+            Technique::NakedPair => "Naked pair",
+            Technique::HiddenPair => "Hidden pair",
+            Technique::NakedTriple => "Naked triple",
+            Technique::HiddenTriple => "Hidden triple",
+            Technique::NakedQuad => "Naked quad",
+            Technique::HiddenQuad => "Hidden quad",
+            Technique::PointingPair => "Pointing pair",
+            Technique::BoxLineReduction => "Box/line reduction",
+            Technique::XWing => "X-Wing",
+            Technique::Swordfish => "Swordfish",
+            Technique::Jellyfish => "Jellyfish",
+            Technique::XYWing => "XY-Wing",
+            Technique::XYZWing => "XYZ-Wing",
+            Technique::SimpleColouring => "Simple colouring"
+        }
+    }
+
+    // find a deduction that can be made with this technique, if there is one
+    fn find(self, grid: &CandidateGrid) -> Option<Step> {
+        match self {
+            Technique::NakedSingle => techniques::naked_single(grid),
+            Technique::HiddenSingle => techniques::hidden_single(grid),
+            Technique::NakedPair => techniques::naked_subset(grid, 2, self),
+            Technique::HiddenPair => techniques::hidden_subset(grid, 2, self),
+            Technique::NakedTriple => techniques::naked_subset(grid, 3, self),
+            Technique::HiddenTriple => techniques::hidden_subset(grid, 3, self),
+            Technique::NakedQuad => techniques::naked_subset(grid, 4, self),
+            Technique::HiddenQuad => techniques::hidden_subset(grid, 4, self),
+            Technique::PointingPair => techniques::pointing(grid),
+            Technique::BoxLineReduction => techniques::box_line_reduction(grid),
+            Technique::XWing => techniques::fish(grid, 2, self),
+            Technique::Swordfish => techniques::fish(grid, 3, self),
+            Technique::Jellyfish => techniques::fish(grid, 4, self),
+            Technique::XYWing => techniques::xy_wing(grid),
+            Technique::XYZWing => techniques::xyz_wing(grid),
+            Technique::SimpleColouring => techniques::simple_colouring(grid)
+        }
+    }
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// A deduction made by the logical solver: the values placed in cells and the
+// candidates eliminated from cells, as (row, col, val), along with the cells
+// whose candidates form the pattern that justifies the deduction. Placing a value
+// also eliminates it from the cell's row, column and block, which is not listed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub technique: Technique,
+    pub placements: Vec<(usize, usize, usize)>,
+    pub eliminations: Vec<(usize, usize, usize)>,
+    pub evidence_cells: Vec<(usize, usize)>
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.technique)?;
+        for (row, col, val) in &self.placements {
+            write!(f, " r{}c{} = {}", row + 1, col + 1, val)?;
+        }
+        if !self.eliminations.is_empty() {
+            write!(f, " removes")?;
+            for (row, col, val) in &self.eliminations {
+                write!(f, " {} from r{}c{}", val, row + 1, col + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// helpers for the logical solver to work with a grid
+impl CandidateGrid {
+    fn apply(&mut self, step: &Step) {
+        for (row, col, val) in &step.placements {
+            self.place(self.cell(*row, *col), *val);
+        }
+        for (row, col, val) in &step.eliminations {
+            self.eliminate(self.cell(*row, *col), *val);
+        }
+    }
+
+    // create a step from cell numbers, listing each cell in order
+    fn step(&self,
+            technique: Technique,
+            mut placements: Vec<(usize, usize)>,
+            mut eliminations: Vec<(usize, usize)>,
+            mut evidence_cells: Vec<usize>) -> Step {
+        placements.sort_unstable();
+        placements.dedup();
+        eliminations.sort_unstable();
+        eliminations.dedup();
+        evidence_cells.sort_unstable();
+        evidence_cells.dedup();
+
+        let with_position = |(cell, val): &(usize, usize)| {
+            let (row, col) = self.position(*cell);
+            (row, col, *val)
+        };
+        Step {
+            technique,
+            placements: placements.iter().map(with_position).collect(),
+            eliminations: eliminations.iter().map(with_position).collect(),
+            evidence_cells: evidence_cells.iter().map(|cell| self.position(*cell)).collect()
+        }
+    }
+}
+
+// Solves a Sudoku step by step, as a person would, making the easiest deduction
+// available at each step. Iterating over the solver makes each deduction in turn,
+// until the Sudoku is solved or none of the techniques can make any progress.
+#[derive(Clone, Debug)]
+pub struct LogicSolver {
+    grid: CandidateGrid
+}
+
+impl LogicSolver {
+    pub fn new(sudoku: &Sudoku) -> Self {
+        Self::from_grid(CandidateGrid::new(sudoku))
+    }
+
+    // start from a grid whose candidates may already have been narrowed down,
+    // e.g. by someone solving it
+    pub fn from_grid(grid: CandidateGrid) -> Self {
+        Self {
+            grid
+        }
+    }
+
+    // find the easiest deduction that can be made next, without making it
+    pub fn find_step(&self) -> Option<Step> {
+        if self.grid.is_solved() {
+            return None;
+        }
+        Technique::ALL.iter().find_map(|technique| technique.find(&self.grid))
+    }
+
+    pub fn apply(&mut self, step: &Step) {
+        self.grid.apply(step);
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.grid.is_solved()
+    }
+
+    // the Sudoku with the values placed so far
+    pub fn sudoku(&self) -> Sudoku {
+        self.grid.to_sudoku()
+    }
+
+    // the values still possible for a cell whose value is not yet known
+    pub fn candidates(&self, row: usize, col: usize) -> Vec<usize> {
+        self.grid.cell_candidates(row, col)
+    }
+
+    pub fn grid(&self) -> &CandidateGrid {
+        &self.grid
+    }
+}
+
+impl Iterator for LogicSolver {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        let step = self.find_step()?;
+        self.apply(&step);
+        Some(step)
+    }
+}
+
+// the steps taken by the logical solver, and how far they got
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogicSolution {
+    pub steps: Vec<Step>,
+    pub sudoku: Sudoku,
+    pub solved: bool
+}
+
+// solve a Sudoku as far as possible using logical techniques alone
+pub fn solve(sudoku: &Sudoku) -> LogicSolution {
+    let mut solver = LogicSolver::new(sudoku);
+    let steps = solver.by_ref().collect();
+    LogicSolution {
+        steps,
+        sudoku: solver.sudoku(),
+        solved: solver.is_solved()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{solve, LogicSolver, Step, Technique};
+    use super::super::generator;
+    use super::super::generator::GeneratorOptions;
+    use super::super::solver;
+    use super::super::sudoku::parse_line;
+    use super::super::{CandidateGrid, Sudoku, Variant};
+
+    // an empty 9x9 grid, with the candidates eliminated from each of the given
+    // cells that are not in the list of values
+    fn grid_with(cells: &[((usize, usize), &[usize])]) -> CandidateGrid {
+        let mut grid = CandidateGrid::new(&Sudoku::new(9));
+        for ((row, col), vals) in cells {
+            for val in 1..=9 {
+                if !vals.contains(&val) {
+                    grid.eliminate(grid.cell(*row, *col), val);
+                }
+            }
+        }
+        grid
+    }
+
+    // an empty 9x9 grid with a value eliminated from the given cells
+    fn grid_without(val: usize, cells: &[(usize, usize)]) -> CandidateGrid {
+        let mut grid = CandidateGrid::new(&Sudoku::new(9));
+        for (row, col) in cells {
+            grid.eliminate(grid.cell(*row, *col), val);
+        }
+        grid
+    }
+
+    // every cell of the given rows, other than those in the given columns
+    fn outside(rows: &[usize], cols: &[usize]) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        for row in rows {
+            cells.extend((0..9).filter(|col| !cols.contains(col)).map(|col| (*row, col)));
+        }
+        cells
+    }
+
+    fn check_sound(sudoku: &Sudoku, steps: &[Step]) {
+        let solution = &solver::solve(sudoku, solver::SolveLimit::First)[0];
+        for step in steps {
+            for (row, col, val) in &step.placements {
+                assert_eq!(Some(*val), solution.cell_value(*row, *col), "{}", step);
+            }
+            for (row, col, val) in &step.eliminations {
+                assert_ne!(Some(*val), solution.cell_value(*row, *col), "{}", step);
+            }
+        }
+    }
+
+    #[test]
+    fn test_singles() {
+        let grid = grid_with(&[((4, 4), &[7])]);
+        let step = Technique::NakedSingle.find(&grid).unwrap();
+        assert_eq!(vec![(4, 4, 7)], step.placements);
+        assert_eq!(vec![(4, 4)], step.evidence_cells);
+
+        let grid = grid_without(5, &outside(&[2], &[6]));
+        let step = Technique::HiddenSingle.find(&grid).unwrap();
+        assert_eq!(vec![(2, 6, 5)], step.placements);
+        assert_eq!(9, step.evidence_cells.len());
+        assert_eq!(None, Technique::NakedSingle.find(&grid));
+    }
+
+    #[test]
+    fn test_subsets() {
+        let grid = grid_with(&[((0, 0), &[1, 2]), ((0, 5), &[1, 2])]);
+        let step = Technique::NakedPair.find(&grid).unwrap();
+        assert_eq!(vec![(0, 0), (0, 5)], step.evidence_cells);
+        assert_eq!(14, step.eliminations.len());
+        assert!(step.eliminations.iter().all(|(row, _, val)| *row == 0 && *val <= 2));
+
+        let grid = grid_with(&[((3, 0), &[1, 2]), ((3, 1), &[2, 3]), ((3, 2), &[1, 3])]);
+        let step = Technique::NakedTriple.find(&grid).unwrap();
+        assert_eq!(vec![(3, 0), (3, 1), (3, 2)], step.evidence_cells);
+        assert_eq!(18, step.eliminations.len());
+        assert!(step.eliminations.iter().all(|(row, col, val)| *row == 3 && *col > 2 && *val <= 3));
+
+        // 1 and 2 can only go in the first two cells of row 0
+        let mut grid = grid_without(1, &outside(&[0], &[0, 1]));
+        for cell in outside(&[0], &[0, 1]) {
+            grid.eliminate(grid.cell(cell.0, cell.1), 2);
+        }
+        let step = Technique::HiddenPair.find(&grid).unwrap();
+        assert_eq!(vec![(0, 0), (0, 1)], step.evidence_cells);
+        assert_eq!(14, step.eliminations.len());
+        assert!(step.eliminations.iter().all(|(_, _, val)| *val > 2));
+        assert_eq!(None, Technique::HiddenTriple.find(&grid));
+    }
+
+    #[test]
+    fn test_intersections() {
+        // in block 0, 4 can only go in row 1
+        let grid = grid_without(4, &[(0, 0), (0, 1), (0, 2), (2, 0), (2, 1), (2, 2)]);
+        let step = Technique::PointingPair.find(&grid).unwrap();
+        assert_eq!(vec![(1, 0), (1, 1), (1, 2)], step.evidence_cells);
+        assert_eq!((3..9).map(|col| (1, col, 4)).collect::<Vec<_>>(), step.eliminations);
+
+        // in row 7, 4 can only go in block 8
+        let grid = grid_without(4, &outside(&[7], &[6, 8]));
+        let step = Technique::BoxLineReduction.find(&grid).unwrap();
+        assert_eq!(vec![(7, 6), (7, 8)], step.evidence_cells);
+        assert_eq!(vec![(6, 6, 4), (6, 7, 4), (6, 8, 4), (8, 6, 4), (8, 7, 4), (8, 8, 4)], step.eliminations);
+    }
+
+    #[test]
+    fn test_fish() {
+        let grid = grid_without(1, &outside(&[0, 4], &[1, 7]));
+        let step = Technique::XWing.find(&grid).unwrap();
+        assert_eq!(vec![(0, 1), (0, 7), (4, 1), (4, 7)], step.evidence_cells);
+        assert_eq!(14, step.eliminations.len());
+        assert!(step.eliminations.iter().all(|(row, col, _)| (*col == 1 || *col == 7) && *row != 0 && *row != 4));
+
+        let grid = grid_without(1, &[outside(&[0], &[0, 3]), outside(&[4], &[3, 6]), outside(&[8], &[0, 6])].concat());
+        assert_eq!(None, Technique::XWing.find(&grid));
+        let step = Technique::Swordfish.find(&grid).unwrap();
+        assert_eq!(6, step.evidence_cells.len());
+        assert_eq!(18, step.eliminations.len());
+
+        let rows = [outside(&[0], &[0, 2]), outside(&[2], &[2, 4]), outside(&[4], &[4, 6]), outside(&[6], &[0, 6])];
+        let grid = grid_without(1, &rows.concat());
+        assert_eq!(None, Technique::Swordfish.find(&grid));
+        let step = Technique::Jellyfish.find(&grid).unwrap();
+        assert_eq!(8, step.evidence_cells.len());
+        assert_eq!(20, step.eliminations.len());
+    }
+
+    #[test]
+    fn test_wings() {
+        let grid = grid_with(&[((0, 0), &[1, 2]), ((0, 5), &[1, 3]), ((5, 0), &[2, 3])]);
+        let step = Technique::XYWing.find(&grid).unwrap();
+        assert_eq!(vec![(0, 0), (0, 5), (5, 0)], step.evidence_cells);
+        assert_eq!(vec![(5, 5, 3)], step.eliminations);
+
+        let grid = grid_with(&[((0, 0), &[1, 2, 3]), ((0, 4), &[1, 3]), ((1, 1), &[2, 3])]);
+        assert_eq!(None, Technique::XYWing.find(&grid));
+        let step = Technique::XYZWing.find(&grid).unwrap();
+        assert_eq!(vec![(0, 0), (0, 4), (1, 1)], step.evidence_cells);
+        assert_eq!(vec![(0, 1, 3), (0, 2, 3)], step.eliminations);
+    }
+
+    #[test]
+    fn test_simple_colouring() {
+        // a chain of conjugate pairs for 1: r1c1 - r1c5 - r4c5 - r4c2
+        let mut cells = outside(&[0], &[0, 4]);
+        cells.extend((1..9).filter(|row| *row != 3).map(|row| (row, 4)));
+        cells.extend(outside(&[3], &[1, 4]));
+        let grid = grid_without(1, &cells);
+        let step = Technique::SimpleColouring.find(&grid).unwrap();
+        assert_eq!(vec![(0, 0), (0, 4), (3, 1), (3, 4)], step.evidence_cells);
+        assert!(step.eliminations.contains(&(1, 1, 1)) && step.eliminations.contains(&(4, 0, 1)));
+        assert!(!step.eliminations.contains(&(1, 2, 1)));
+
+        // r1c1 - r1c5 - r5c5 - r5c2 - r3c2, where r1c1 and r3c2 have the same
+        // colour but see each other, so 1 cannot go in any cell of that colour
+        let mut cells = outside(&[0], &[0, 4]);
+        cells.extend((1..9).filter(|row| *row != 4).map(|row| (row, 4)));
+        cells.extend(outside(&[4], &[1, 4]));
+        cells.extend((0..9).filter(|row| *row != 2 && *row != 4).map(|row| (row, 1)));
+        let grid = grid_without(1, &cells);
+        let step = Technique::SimpleColouring.find(&grid).unwrap();
+        assert_eq!(vec![(0, 0, 1), (2, 1, 1), (4, 4, 1)], step.eliminations);
+    }
+
+    #[test]
+    fn test_solve() {
+        // an easy puzzle can be solved with singles alone
+        let sudoku = parse_line("3.5...9..4..9..7.29.6..1....1.45..89.4.1.2.7.75..83.2....2..3.61.2..6..7..4...2.1");
+        let solution = solve(&sudoku);
+        assert!(solution.solved);
+        assert_eq!(solver::solve(&sudoku, solver::SolveLimit::First)[0], solution.sudoku);
+        assert!(solution.steps.iter().all(|step| step.technique <= Technique::HiddenSingle));
+        check_sound(&sudoku, &solution.steps);
+
+        // a harder one needs more, and every step taken must be sound
+        let sudoku = parse_line(".4.....8.8..3.2..5..7.1.6...6.....3...5.7.1...8.....5...1.4.5..3..6.9..4.2.....9.");
+        let solution = solve(&sudoku);
+        assert!(solution.solved);
+        assert!(solution.steps.iter().any(|step| step.technique == Technique::Swordfish));
+        check_sound(&sudoku, &solution.steps);
+
+        for seed in 0..20 {
+            let options = GeneratorOptions { seed, ..Default::default() };
+            let sudoku = generator::generate(&options).unwrap();
+            check_sound(&sudoku, &solve(&sudoku).steps);
+        }
+
+        // the blocks need not be square
+        for size in &[6, 8, 12] {
+            let options = GeneratorOptions { size: *size, ..Default::default() };
+            let sudoku = generator::generate(&options).unwrap();
+            check_sound(&sudoku, &solve(&sudoku).steps);
+        }
+
+        // and the diagonals of a Sudoku-X are units too
+        for seed in 0..5 {
+            let options = GeneratorOptions { variant: Variant::X, seed, ..Default::default() };
+            let sudoku = generator::generate(&options).unwrap();
+            check_sound(&sudoku, &solve(&sudoku).steps);
+        }
+    }
+
+    #[test]
+    fn test_logic_solver() {
+        let sudoku: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        let mut solver = LogicSolver::new(&sudoku);
+        assert_eq!(vec![2], solver.candidates(0, 1));
+        assert!(solver.candidates(0, 0).is_empty());
+        let step = solver.find_step().unwrap();
+        assert_eq!(Some(step.clone()), solver.next());
+        assert_eq!(8, solver.by_ref().count() + 1);
+        assert!(solver.is_solved());
+        assert_eq!(None, solver.find_step());
+        assert!(solver.sudoku().is_completed());
+
+        // candidates already eliminated from a grid are taken into account
+        let mut grid = CandidateGrid::new(&Sudoku::new(4));
+        for val in 2..=4 {
+            grid.eliminate_candidate(0, 0, val);
+        }
+        let mut solver = LogicSolver::from_grid(grid);
+        assert_eq!(Some(vec![(0, 0, 1)]), solver.next().map(|step| step.placements));
+        assert_eq!(Some(1), solver.grid().cell_value(0, 0));
+    }
+}
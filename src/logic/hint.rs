@@ -0,0 +1,202 @@
+use super::super::candidates::{bit, values};
+use super::super::solver;
+use super::super::solver::CandidateCheck;
+use super::super::{CandidateGrid, Sudoku};
+use super::{Step, Technique};
+
+// What to tell someone who asks for help with a partially filled grid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Hint {
+    // the easiest deduction that can be made next
+    Step(Step),
+    // the value of a cell, revealed from the solution when none of the techniques
+    // can make any progress
+    Reveal { row: usize, col: usize, val: usize },
+    // the grid cannot be completed, because of the entries made so far
+    Contradiction(Contradiction)
+}
+
+// Why a grid cannot be completed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Contradiction {
+    // a value appears in more than one of these cells of a row, column or block
+    Repeated { val: usize, cells: Vec<(usize, usize)> },
+    // an empty cell has no possible values left
+    NoCandidates { row: usize, col: usize },
+    // a value has no place left in the row, column or block with these cells
+    NoPlace { val: usize, cells: Vec<(usize, usize)> },
+    // the grid has no solution, though this only shows up further on
+    NoSolution,
+    // the values in the grid have a solution, but these of its values, as
+    // (row, col, val), have been eliminated from the candidates
+    SolutionEliminated { eliminated: Vec<(usize, usize, usize)> }
+}
+
+// Find the easiest hint for a grid, or None if it is already complete and
+// correct. If the puzzle has a unique solution, any entry that differs from it
+// leaves the grid with no solution, so wrong entries are always reported as a
+// contradiction, rather than leading to further hints. The candidates for each
+// cell are worked out from the entries in the grid, so a hint that only
+// eliminates candidates is given again until a value is placed (unless the
+// candidates are kept in a grid, see hint_for_grid).
+pub fn hint(sudoku: &Sudoku) -> Option<Hint> {
+    hint_for_grid(&CandidateGrid::new(sudoku))
+}
+
+// as for hint, but taking into account the candidates already eliminated from a
+// grid, e.g. by following earlier hints
+pub fn hint_for_grid(grid: &CandidateGrid) -> Option<Hint> {
+    if let Some(contradiction) = find_contradiction(grid) {
+        return Some(Hint::Contradiction(contradiction));
+    }
+    if grid.is_solved() {
+        return None;
+    }
+
+    let solution = match solver::check_candidates(grid) {
+        CandidateCheck::Consistent(solution) => solution,
+        CandidateCheck::Excluded { eliminated, .. } =>
+            return Some(Hint::Contradiction(Contradiction::SolutionEliminated { eliminated })),
+        CandidateCheck::Unsolvable => return Some(Hint::Contradiction(Contradiction::NoSolution))
+    };
+
+    let step = Technique::ALL.iter().find_map(|technique| technique.find(grid));
+    Some(match step {
+        Some(step) => Hint::Step(step),
+        None => {
+            // reveal the cell with the fewest possibilities
+            let cell = (0..grid.num_cells()).filter(|cell| grid.values[*cell].is_none())
+                                            .min_by_key(|cell| grid.candidates(*cell).count_ones())?;
+            let (row, col) = grid.position(cell);
+            let val = solution.cell_value(row, col)?;
+            Hint::Reveal { row, col, val }
+        }
+    })
+}
+
+// find a contradiction that is apparent from the candidates alone
+fn find_contradiction(grid: &CandidateGrid) -> Option<Contradiction> {
+    let positions = |cells: Vec<usize>| cells.into_iter().map(|cell| grid.position(cell)).collect();
+    for unit in &grid.units {
+        for val in 1..=grid.dimension {
+            let placed: Vec<usize> = unit.iter().copied().filter(|cell| grid.values[*cell] == Some(val)).collect();
+            if placed.len() > 1 {
+                return Some(Contradiction::Repeated { val, cells: positions(placed) });
+            }
+        }
+    }
+
+    for cell in 0..grid.num_cells() {
+        if grid.values[cell].is_none() && grid.candidates(cell) == 0 {
+            let (row, col) = grid.position(cell);
+            return Some(Contradiction::NoCandidates { row, col });
+        }
+    }
+
+    for unit in &grid.units {
+        let placed = unit.iter().fold(0, |placed, cell| placed | grid.values[*cell].map_or(0, bit));
+        let possible = unit.iter().fold(placed, |possible, cell| possible | grid.candidates(*cell));
+        if let Some(val) = values(!possible).find(|val| *val <= grid.dimension) {
+            return Some(Contradiction::NoPlace { val, cells: positions(unit.clone()) });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hint, hint_for_grid, Contradiction, Hint};
+    use super::super::super::{solver, CandidateGrid, Sudoku};
+    use super::super::super::sudoku::parse_line;
+    use super::super::{LogicSolver, Technique};
+
+    #[test]
+    fn test_hint() {
+        let mut sudoku = parse_line("3.5...9..4..9..7.29.6..1....1.45..89.4.1.2.7.75..83.2....2..3.61.2..6..7..4...2.1");
+        match hint(&sudoku) {
+            Some(Hint::Step(step)) => {
+                assert_eq!(Technique::NakedSingle, step.technique);
+                assert_eq!(1, step.placements.len());
+            }
+            other => panic!("expected a step, not {:?}", other)
+        }
+
+        // following the hints solves the puzzle
+        while let Some(Hint::Step(step)) = hint(&sudoku) {
+            for (row, col, val) in step.placements {
+                sudoku.set_cell_value(row, col, val);
+            }
+        }
+        assert!(sudoku.is_completed());
+        assert_eq!(None, hint(&sudoku));
+    }
+
+    #[test]
+    fn test_hint_for_grid() {
+        // logic stalls on this puzzle after eliminating some candidates, but
+        // following the hints, eliminations included, still completes it
+        let sudoku = parse_line("..67..4......5..7..7.1...3.8...79.16.6.3.175.7..62...469...7.23.3796..4...8...967");
+        let solution = &solver::solve(&sudoku, solver::SolveLimit::First)[0];
+        let mut grid = CandidateGrid::new(&sudoku);
+        let mut revealed = 0;
+        while let Some(hint) = hint_for_grid(&grid) {
+            match hint {
+                Hint::Step(step) => {
+                    for (row, col, val) in step.placements {
+                        grid.set_cell_value(row, col, val);
+                    }
+                    for (row, col, val) in step.eliminations {
+                        grid.eliminate_candidate(row, col, val);
+                    }
+                }
+                Hint::Reveal { row, col, val } => {
+                    grid.set_cell_value(row, col, val);
+                    revealed += 1;
+                }
+                Hint::Contradiction(contradiction) => panic!("unexpected {:?}", contradiction)
+            }
+        }
+        assert!(revealed > 0);
+        assert_eq!(*solution, grid.to_sudoku());
+
+        // a candidate eliminated by mistake
+        let mut grid = CandidateGrid::new(&sudoku);
+        let val = solution.cell_value(0, 0).unwrap();
+        grid.eliminate_candidate(0, 0, val);
+        assert_eq!(Some(Hint::Contradiction(Contradiction::SolutionEliminated { eliminated: vec![(0, 0, val)] })),
+                   hint_for_grid(&grid));
+    }
+
+    #[test]
+    fn test_hint_reveal() {
+        // none of the techniques make any progress on an empty grid
+        let sudoku = Sudoku::new(4);
+        let solution = &solver::solve(&sudoku, solver::SolveLimit::First)[0];
+        let val = solution.cell_value(0, 0).unwrap();
+        assert_eq!(Some(Hint::Reveal { row: 0, col: 0, val }), hint(&sudoku));
+    }
+
+    #[test]
+    fn test_hint_contradiction() {
+        let sudoku: Sudoku = "3.3. .... .... ....".parse().unwrap();
+        assert_eq!(Some(Hint::Contradiction(Contradiction::Repeated { val: 3, cells: vec![(0, 0), (0, 2)] })),
+                   hint(&sudoku));
+
+        // r1c2 sees 1, 2, 3 and 4
+        let sudoku: Sudoku = "1... .2.. .3.. .4..".parse().unwrap();
+        assert_eq!(Some(Hint::Contradiction(Contradiction::NoCandidates { row: 0, col: 1 })), hint(&sudoku));
+
+        // 1 cannot go anywhere in the second row
+        let sudoku: Sudoku = "..1. 2... .1.. ....".parse().unwrap();
+        assert_eq!(Some(Hint::Contradiction(Contradiction::NoPlace { val: 1, cells: vec![(1, 0), (1, 1), (1, 2), (1, 3)] })),
+                   hint(&sudoku));
+
+        // an entry that differs from the unique solution, but is still a candidate
+        let mut sudoku = parse_line("3.5...9..4..9..7.29.6..1....1.45..89.4.1.2.7.75..83.2....2..3.61.2..6..7..4...2.1");
+        let solution = &solver::solve(&sudoku, solver::SolveLimit::First)[0];
+        let candidates = LogicSolver::new(&sudoku).candidates(0, 1);
+        let val = candidates.into_iter().find(|val| solution.cell_value(0, 1) != Some(*val)).unwrap();
+        sudoku.set_cell_value(0, 1, val);
+        assert!(matches!(hint(&sudoku), Some(Hint::Contradiction(_))));
+    }
+}
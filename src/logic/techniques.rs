@@ -0,0 +1,330 @@
+use std::collections::VecDeque;
+
+use super::super::candidates::{bit, values};
+use super::super::CandidateGrid;
+use super::{Step, Technique};
+
+// A cell with only one candidate must take that value.
+pub(super) fn naked_single(grid: &CandidateGrid) -> Option<Step> {
+    (0..grid.num_cells()).find(|cell| grid.candidates(*cell).count_ones() == 1).map(|cell| {
+        let val = values(grid.candidates(cell)).next().unwrap_or_default();
+        grid.step(Technique::NakedSingle, vec![(cell, val)], Vec::new(), vec![cell])
+    })
+}
+
+// A value that has only one place left in a unit must go there.
+pub(super) fn hidden_single(grid: &CandidateGrid) -> Option<Step> {
+    for unit in &grid.units {
+        for val in 1..=grid.dimension {
+            let cells = unit.iter().filter(|cell| grid.has_candidate(**cell, val));
+            if let [cell] = cells.copied().collect::<Vec<_>>().as_slice() {
+                return Some(grid.step(Technique::HiddenSingle, vec![(*cell, val)], Vec::new(), unit.clone()));
+            }
+        }
+    }
+    None
+}
+
+// When a number of cells of a unit have only that many candidates between them,
+// those values must go in those cells, so can be eliminated from the rest of the
+// unit.
+pub(super) fn naked_subset(grid: &CandidateGrid, size: usize, technique: Technique) -> Option<Step> {
+    for unit in &grid.units {
+        let cells: Vec<usize> = unit.iter()
+                                    .copied()
+                                    .filter(|cell| (2..=size).contains(&(grid.candidates(*cell).count_ones() as usize)))
+                                    .collect();
+        let found = find_combination(&cells, size, &mut |subset| {
+            let subset_vals = subset.iter().fold(0, |vals, cell| vals | grid.candidates(*cell));
+            if subset_vals.count_ones() as usize != size {
+                return None;
+            }
+            let eliminations = eliminations(grid, unit.iter().filter(|cell| !subset.contains(cell)), subset_vals);
+            if eliminations.is_empty() {
+                None
+            } else {
+                Some(grid.step(technique, Vec::new(), eliminations, subset.to_vec()))
+            }
+        });
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+// When a number of values only have that many places left between them in a
+// unit, those cells must hold those values, so any other candidates can be
+// eliminated from them.
+pub(super) fn hidden_subset(grid: &CandidateGrid, size: usize, technique: Technique) -> Option<Step> {
+    for unit in &grid.units {
+        // the positions of each value within the unit
+        let positions: Vec<u64> = (1..=grid.dimension).map(|val| {
+            unit.iter()
+                .enumerate()
+                .filter(|(_, cell)| grid.has_candidate(**cell, val))
+                .fold(0, |positions, (i, _)| positions | 1 << i)
+        }).collect();
+        let vals: Vec<usize> = (1..=grid.dimension)
+                                   .filter(|val| (2..=size).contains(&(positions[val - 1].count_ones() as usize)))
+                                   .collect();
+
+        let found = find_combination(&vals, size, &mut |subset| {
+            let subset_positions = subset.iter().fold(0, |all, val| all | positions[val - 1]);
+            if subset_positions.count_ones() as usize != size {
+                return None;
+            }
+            let subset_cells: Vec<usize> = values(subset_positions).map(|i| unit[i - 1]).collect();
+            let subset_vals = subset.iter().fold(0, |vals, val| vals | bit(*val));
+            let eliminations = eliminations(grid, subset_cells.iter(), !subset_vals);
+            if eliminations.is_empty() {
+                None
+            } else {
+                Some(grid.step(technique, Vec::new(), eliminations, subset_cells))
+            }
+        });
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+// When the places for a value in a block all lie in one row or column, the value
+// must go in that part of the line, so can be eliminated from the rest of it.
+pub(super) fn pointing(grid: &CandidateGrid) -> Option<Step> {
+    intersection(grid, Technique::PointingPair, CandidateGrid::BLOCK, &[CandidateGrid::ROW, CandidateGrid::COL])
+}
+
+// When the places for a value in a row or column all lie in one block, the value
+// must go in that part of the block, so can be eliminated from the rest of it.
+pub(super) fn box_line_reduction(grid: &CandidateGrid) -> Option<Step> {
+    intersection(grid, Technique::BoxLineReduction, CandidateGrid::ROW, &[CandidateGrid::BLOCK])
+        .or_else(|| intersection(grid, Technique::BoxLineReduction, CandidateGrid::COL, &[CandidateGrid::BLOCK]))
+}
+
+fn intersection(grid: &CandidateGrid, technique: Technique, kind: usize, other_kinds: &[usize]) -> Option<Step> {
+    let units = kind * grid.dimension..(kind + 1) * grid.dimension;
+    for unit in units {
+        for val in 1..=grid.dimension {
+            let cells = grid.cells_with(unit, val);
+            if cells.len() < 2 {
+                continue;
+            }
+            for other_kind in other_kinds {
+                let other_unit = grid.cell_units[cells[0]][*other_kind];
+                if cells.iter().all(|cell| grid.cell_units[*cell][*other_kind] == other_unit) {
+                    let eliminations: Vec<(usize, usize)> = grid.cells_with(other_unit, val)
+                                                                .into_iter()
+                                                                .filter(|cell| !cells.contains(cell))
+                                                                .map(|cell| (cell, val))
+                                                                .collect();
+                    if !eliminations.is_empty() {
+                        return Some(grid.step(technique, Vec::new(), eliminations, cells));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+// When the places for a value in a number of rows lie in only that many columns
+// between them, the value must go in those places, so can be eliminated from the
+// rest of those columns (and likewise with the rows and columns swapped). With
+// two lines this is an X-Wing, with three a Swordfish and with four a Jellyfish.
+pub(super) fn fish(grid: &CandidateGrid, size: usize, technique: Technique) -> Option<Step> {
+    let dimension = grid.dimension;
+    for val in 1..=dimension {
+        for (base_kind, cover_kind) in &[(CandidateGrid::ROW, CandidateGrid::COL), (CandidateGrid::COL, CandidateGrid::ROW)] {
+            // the places for the value in each base line, by the index of the cover line
+            let base_lines: Vec<(usize, u64)> = (0..dimension).map(|line| {
+                let unit = base_kind * dimension + line;
+                let covers: u64 = grid.cells_with(unit, val)
+                                 .iter()
+                                 .fold(0, |covers, cell| covers | 1 << (grid.cell_units[*cell][*cover_kind] % dimension));
+                (line, covers)
+            }).filter(|(_, covers)| (2..=size).contains(&(covers.count_ones() as usize))).collect();
+
+            let found = find_combination(&base_lines, size, &mut |subset| {
+                let covers = subset.iter().fold(0, |all, (_, covers)| all | covers);
+                if covers.count_ones() as usize != size {
+                    return None;
+                }
+                let base_units: Vec<usize> = subset.iter().map(|(line, _)| base_kind * dimension + line).collect();
+                let mut eliminations = Vec::new();
+                for cover_line in values(covers) {
+                    let cover_unit = cover_kind * dimension + cover_line - 1;
+                    eliminations.extend(grid.cells_with(cover_unit, val)
+                                            .into_iter()
+                                            .filter(|cell| !base_units.contains(&grid.cell_units[*cell][*base_kind]))
+                                            .map(|cell| (cell, val)));
+                }
+                if eliminations.is_empty() {
+                    None
+                } else {
+                    let evidence_cells = base_units.iter().flat_map(|unit| grid.cells_with(*unit, val)).collect();
+                    Some(grid.step(technique, Vec::new(), eliminations, evidence_cells))
+                }
+            });
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+    None
+}
+
+// A cell with candidates xy sees cells with candidates xz and yz. Whichever of x
+// and y the first cell holds, one of the others must be z, so z can be eliminated
+// from any cell that sees both of them.
+pub(super) fn xy_wing(grid: &CandidateGrid) -> Option<Step> {
+    let bivalue: Vec<usize> = (0..grid.num_cells()).filter(|cell| grid.candidates(*cell).count_ones() == 2).collect();
+    for pivot in &bivalue {
+        let pivot_vals = grid.candidates(*pivot);
+        for first in bivalue.iter().filter(|cell| grid.sees(*pivot, **cell)) {
+            let shared = pivot_vals & grid.candidates(*first);
+            if shared.count_ones() != 1 {
+                continue;
+            }
+            let z = grid.candidates(*first) & !shared;
+            let second_vals = (pivot_vals & !shared) | z;
+            for second in bivalue.iter().filter(|cell| grid.sees(*pivot, **cell) && grid.candidates(**cell) == second_vals) {
+                let found = wing(grid, Technique::XYWing, &[*pivot, *first, *second], &[*first, *second], z);
+                if found.is_some() {
+                    return found;
+                }
+            }
+        }
+    }
+    None
+}
+
+// A cell with candidates xyz sees cells with candidates xz and yz. One of the
+// three must be z, so z can be eliminated from any cell that sees all of them.
+pub(super) fn xyz_wing(grid: &CandidateGrid) -> Option<Step> {
+    let bivalue: Vec<usize> = (0..grid.num_cells()).filter(|cell| grid.candidates(*cell).count_ones() == 2).collect();
+    for pivot in (0..grid.num_cells()).filter(|cell| grid.candidates(*cell).count_ones() == 3) {
+        let pivot_vals = grid.candidates(pivot);
+        let pincers: Vec<usize> = bivalue.iter()
+                                         .copied()
+                                         .filter(|cell| grid.sees(pivot, *cell) && grid.candidates(*cell) & !pivot_vals == 0)
+                                         .collect();
+        for (i, first) in pincers.iter().enumerate() {
+            for second in &pincers[i + 1..] {
+                let z = grid.candidates(*first) & grid.candidates(*second);
+                if z.count_ones() != 1 || grid.candidates(*first) | grid.candidates(*second) != pivot_vals {
+                    continue;
+                }
+                let found = wing(grid, Technique::XYZWing, &[pivot, *first, *second], &[pivot, *first, *second], z);
+                if found.is_some() {
+                    return found;
+                }
+            }
+        }
+    }
+    None
+}
+
+// eliminate a value from any cell that sees all of the given cells
+fn wing(grid: &CandidateGrid, technique: Technique, cells: &[usize], seen: &[usize], z: u64) -> Option<Step> {
+    let val = values(z).next()?;
+    let eliminations: Vec<(usize, usize)> = (0..grid.num_cells())
+        .filter(|cell| grid.has_candidate(*cell, val) && seen.iter().all(|other| grid.sees(*cell, *other)))
+        .map(|cell| (cell, val))
+        .collect();
+    if eliminations.is_empty() {
+        None
+    } else {
+        Some(grid.step(technique, Vec::new(), eliminations, cells.to_vec()))
+    }
+}
+
+// Link the places for a value that are the only two in a unit, and colour each
+// chain of links with alternating colours, so that the value must go in all the
+// cells of one of the colours. If two cells of the same colour see each other,
+// then the value cannot go in any cell of that colour. Otherwise, it cannot go in
+// a cell that sees cells of both colours.
+pub(super) fn simple_colouring(grid: &CandidateGrid) -> Option<Step> {
+    for val in 1..=grid.dimension {
+        let mut links = vec![Vec::new(); grid.num_cells()];
+        for unit in 0..grid.units.len() {
+            if let [first, second] = grid.cells_with(unit, val).as_slice() {
+                links[*first].push(*second);
+                links[*second].push(*first);
+            }
+        }
+
+        let mut colours: Vec<Option<bool>> = vec![None; grid.num_cells()];
+        for start in 0..grid.num_cells() {
+            if links[start].is_empty() || colours[start].is_some() {
+                continue;
+            }
+
+            // colour the chain with a breadth-first search
+            let mut chain = Vec::new();
+            let mut queue = VecDeque::new();
+            colours[start] = Some(false);
+            queue.push_back(start);
+            while let Some(cell) = queue.pop_front() {
+                chain.push(cell);
+                for linked in &links[cell] {
+                    if colours[*linked].is_none() {
+                        colours[*linked] = colours[cell].map(|colour| !colour);
+                        queue.push_back(*linked);
+                    }
+                }
+            }
+
+            let (first, second): (Vec<usize>, Vec<usize>) = chain.iter().partition(|cell| colours[**cell] == Some(false));
+            for same in &[&first, &second] {
+                if same.iter().any(|cell| same.iter().any(|other| grid.sees(*cell, *other))) {
+                    let eliminations = same.iter().map(|cell| (*cell, val)).collect();
+                    return Some(grid.step(Technique::SimpleColouring, Vec::new(), eliminations, chain));
+                }
+            }
+
+            let eliminations: Vec<(usize, usize)> = (0..grid.num_cells())
+                .filter(|cell| grid.has_candidate(*cell, val) && colours[*cell].is_none())
+                .filter(|cell| first.iter().any(|other| grid.sees(*cell, *other)) &&
+                               second.iter().any(|other| grid.sees(*cell, *other)))
+                .map(|cell| (cell, val))
+                .collect();
+            if !eliminations.is_empty() {
+                return Some(grid.step(Technique::SimpleColouring, Vec::new(), eliminations, chain));
+            }
+        }
+    }
+    None
+}
+
+// the candidates in a set of values eliminated from some cells
+fn eliminations<'a>(grid: &CandidateGrid, cells: impl Iterator<Item = &'a usize>, vals: u64) -> Vec<(usize, usize)> {
+    cells.flat_map(|cell| values(grid.candidates(*cell) & vals).map(move |val| (*cell, val))).collect()
+}
+
+// find the first combination of a number of items for which a function finds
+// something
+fn find_combination<T: Copy, R>(items: &[T], size: usize, f: &mut impl FnMut(&[T]) -> Option<R>) -> Option<R> {
+    fn choose<T: Copy, R>(items: &[T],
+                          size: usize,
+                          chosen: &mut Vec<T>,
+                          f: &mut impl FnMut(&[T]) -> Option<R>) -> Option<R> {
+        if chosen.len() == size {
+            return f(chosen);
+        }
+        for (i, item) in items.iter().enumerate() {
+            if items.len() - i < size - chosen.len() {
+                break;
+            }
+            chosen.push(*item);
+            let found = choose(&items[i + 1..], size, chosen, f);
+            chosen.pop();
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+    choose(items, size, &mut Vec::with_capacity(size), f)
+}
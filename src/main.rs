@@ -1,30 +1,58 @@
-mod dlx;
-mod matrix;
-mod solver;
-mod sudoku;
-
+use std::ffi::OsStr;
 use std::fs;
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process;
-use std::time::Instant;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-use crate::sudoku::Sudoku;
+use sudoku::dlx::{ColumnChooser, FirstColumn, MinRemainingValues, RandomMinRemainingValues};
+use sudoku::generator;
+use sudoku::generator::GeneratorOptions;
+use sudoku::grading;
+use sudoku::grading::Grade;
+use sudoku::analysis;
+use sudoku::solver;
+use sudoku::solver::{AbortReason, SolveLimit, SolveOptions, SolveOutcome, Uniqueness};
+use sudoku::stats::SearchStats;
+use sudoku::{Sudoku, SudokuError};
 
 const ALL_STR: &str = "--all";
-const USAGE_STR: &str = "Usage: sudoku filename|starting_configuration [-all]";
+const COUNT_STR: &str = "--count";
+const STATS_STR: &str = "--stats";
+const TIMEOUT_STR: &str = "--timeout";
+const THREADS_STR: &str = "--threads";
+const HEURISTIC_STR: &str = "--heuristic";
+const GENERATE_STR: &str = "generate";
+const ANALYZE_STR: &str = "analyze";
+const GRADE_STR: &str = "grade";
+const SIZE_STR: &str = "--size";
+const CLUES_STR: &str = "--clues";
+const SYMMETRY_STR: &str = "--symmetry";
+const SEED_STR: &str = "--seed";
+const BLOCK_STR: &str = "--block";
+const VARIANT_STR: &str = "--variant";
+const USAGE_STR: &str = "Usage: sudoku filename|starting_configuration [--all|--count] [--stats] \
+                         [--timeout seconds] [--threads n] [--heuristic mrv|first|random[:seed]] \
+                         [--block HxW] [--variant standard|x]\n       \
+                         sudoku generate [--size n] [--clues n] [--symmetry none|rot180|diagonal|mirror] \
+                         [--variant standard|x] [--seed n]\n       \
+                         sudoku analyze filename|starting_configuration [--block HxW] [--variant standard|x]\n       \
+                         sudoku grade filename|directory... [--timeout seconds] [--block HxW] [--variant standard|x]";
+// the switches that are followed by a value
+const VALUE_SWITCHES: [&str; 9] = [TIMEOUT_STR, THREADS_STR, HEURISTIC_STR, SIZE_STR, CLUES_STR, SYMMETRY_STR, SEED_STR,
+                                   BLOCK_STR, VARIANT_STR];
 const FILENAME_PARAM: usize = 1;
-const ALL_PARAM: usize = 2;
 
-fn read_sudoku_str(args: &Vec<String>) -> String {
+fn read_sudoku_str(args: &[String], param: usize) -> String {
     // check for correct number of parameters
-    if args.len() < FILENAME_PARAM + 1 {
+    if args.len() < param + 1 {
         println!("{}", USAGE_STR);
         process::exit(1);
     }
 
     // check if the param is a file and read it in
-    let param = &args[FILENAME_PARAM];
+    let param = &args[param];
     if Path::new(param).is_file() {
         if let Ok(sudoku_str) = fs::read_to_string(param) {
             sudoku_str
@@ -37,49 +65,408 @@ fn read_sudoku_str(args: &Vec<String>) -> String {
     }
 }
 
-fn terminate_on_first(args: &Vec<String>) -> bool {
-    if args.len() >= ALL_PARAM + 1 {
-        args[ALL_PARAM] != ALL_STR
+// parse a Sudoku, with the shape of block given by the --block switch if there
+// is one, or else the default shape for its size, and the variant given by the
+// --variant switch
+fn parse_sudoku(args: &[String], sudoku_str: &str) -> Result<Sudoku, SudokuError> {
+    let mut sudoku = parse_sudoku_grid(args, sudoku_str)?;
+    if let Some(variant) = parse_switch(args, VARIANT_STR) {
+        sudoku.set_variant(variant);
+    }
+    Ok(sudoku)
+}
+
+fn parse_sudoku_grid(args: &[String], sudoku_str: &str) -> Result<Sudoku, SudokuError> {
+    if let Some(value) = switch_value(args, BLOCK_STR) {
+        match parse_block_shape(value) {
+            Some((height, width)) => Sudoku::parse_with_block_shape(sudoku_str, height, width),
+            None => {
+                println!("Invalid block shape: {}\n{}", value, USAGE_STR);
+                process::exit(1);
+            }
+        }
+    } else if has_switch(args, BLOCK_STR) {
+        println!("{}", USAGE_STR);
+        process::exit(1);
     } else {
-        true
+        sudoku_str.parse()
     }
 }
 
-fn main() {
-    let args = env::args().collect();
-    let sudoku_str = &read_sudoku_str(&args);
-    let sudoku = Sudoku::from(sudoku_str);
+// get the height and width of a block shape, e.g. (2, 3) for "2x3"
+fn parse_block_shape(value: &str) -> Option<(usize, usize)> {
+    let (height, width) = value.split_once('x')?;
+    Some((height.parse().ok()?, width.parse().ok()?))
+}
 
-    println!("Initial Sudoku ({}/{}) is:\n{}",
-             sudoku.num_completed_cells(),
-             sudoku.num_cells(),
-             sudoku);
+// read the Sudoku given as a parameter, exiting if it cannot be parsed
+fn read_sudoku(args: &[String], param: usize) -> Sudoku {
+    match parse_sudoku(args, &read_sudoku_str(args, param)) {
+        Ok(sudoku) => sudoku,
+        Err(err) => {
+            println!("{}", err);
+            process::exit(exit_code(&err));
+        }
+    }
+}
 
-    if sudoku.is_consistent() {
-        let start_time = Instant::now();
-        let solutions = solver::solve(&sudoku, terminate_on_first(&args));
+// check whether a switch was given after the puzzle input file name / string
+fn has_switch(args: &[String], switch: &str) -> bool {
+    args.iter().skip(FILENAME_PARAM + 1).any(|arg| arg == switch)
+}
+
+// get the value given after a switch, e.g. "10" for --timeout 10
+fn switch_value<'a>(args: &'a [String], switch: &str) -> Option<&'a str> {
+    let mut switch_args = args.iter().skip(FILENAME_PARAM + 1);
+    while let Some(arg) = switch_args.next() {
+        if arg == switch {
+            return switch_args.next().map(|value| value.as_str());
+        }
+    }
+    None
+}
+
+// parse the value given after a switch, if the switch was given
+fn parse_switch<T: FromStr>(args: &[String], switch: &str) -> Option<T> {
+    if let Some(value) = switch_value(args, switch) {
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                println!("Invalid value for {}: {}\n{}", switch, value, USAGE_STR);
+                process::exit(1);
+            }
+        }
+    } else if has_switch(args, switch) {
+        println!("{}", USAGE_STR);
+        process::exit(1);
+    } else {
+        None
+    }
+}
+
+fn solve_options(args: &[String]) -> SolveOptions {
+    let mut options = SolveOptions::default();
+    if let Some(value) = switch_value(args, TIMEOUT_STR) {
+        // negative, infinite and overly large timeouts have no Duration
+        match value.parse::<f64>().ok().and_then(|secs| Duration::try_from_secs_f64(secs).ok()) {
+            Some(timeout) => options.timeout = Some(timeout),
+            None => {
+                println!("Invalid timeout: {}\n{}", value, USAGE_STR);
+                process::exit(1);
+            }
+        }
+    } else if has_switch(args, TIMEOUT_STR) {
+        println!("{}", USAGE_STR);
+        process::exit(1);
+    }
+
+    if let Some(value) = switch_value(args, THREADS_STR) {
+        match value.parse::<usize>() {
+            Ok(threads) if threads > 0 => options.threads = threads,
+            _ => {
+                println!("Invalid number of threads: {}\n{}", value, USAGE_STR);
+                process::exit(1);
+            }
+        }
+    } else if has_switch(args, THREADS_STR) {
+        println!("{}", USAGE_STR);
+        process::exit(1);
+    }
+
+    if let Some(value) = switch_value(args, HEURISTIC_STR) {
+        match parse_heuristic(value) {
+            Some(chooser) => options.chooser = chooser,
+            None => {
+                println!("Invalid heuristic: {}\n{}", value, USAGE_STR);
+                process::exit(1);
+            }
+        }
+    } else if has_switch(args, HEURISTIC_STR) {
+        println!("{}", USAGE_STR);
+        process::exit(1);
+    }
+    options
+}
+
+// get the column chooser named by a heuristic, e.g. "random:42" for randomised
+// minimum remaining values with a seed of 42
+fn parse_heuristic(value: &str) -> Option<Box<dyn ColumnChooser>> {
+    match value {
+        "mrv" => Some(Box::new(MinRemainingValues)),
+        "first" => Some(Box::new(FirstColumn)),
+        "random" => Some(Box::new(RandomMinRemainingValues::new(0))),
+        _ => {
+            let seed = value.strip_prefix("random:")?.parse().ok()?;
+            Some(Box::new(RandomMinRemainingValues::new(seed)))
+        }
+    }
+}
+
+fn print_aborted(aborted: Option<AbortReason>) {
+    if let Some(reason) = aborted {
+        println!("\nThe search {} before it was complete.", reason);
+    }
+}
+
+fn terminate_on_first(args: &[String]) -> bool {
+    !has_switch(args, ALL_STR)
+}
+
+fn print_stats(args: &[String], stats: &SearchStats) {
+    if has_switch(args, STATS_STR) {
+        println!("\nSearch statistics:\n{}", stats);
+    }
+}
+
+// print the solutions as they are found, rather than storing them all, returning
+// how many there were
+fn print_solutions(args: &[String],
+                   mut solutions: impl Iterator<Item = Sudoku>,
+                   start_time: Instant) -> usize {
+    let mut num_solutions = 0;
+
+    if terminate_on_first(args) {
+        if let Some(solution) = solutions.next() {
+            num_solutions += 1;
+            let elapsed_time = start_time.elapsed();
+            println!("\nFound 1 solution in {:.1?}:", elapsed_time);
+            println!("{}", solution);
+        }
+    } else {
+        for solution in solutions {
+            num_solutions += 1;
+            println!("\n{}:", num_solutions);
+            println!("{}", solution);
+        }
         let elapsed_time = start_time.elapsed();
 
-        let num_solutions = solutions.len();
         if num_solutions > 0 {
             let mut plural = "";
             if num_solutions > 1 {
                 plural = "s";
             }
+            println!("\nFound {} solution{} in {:.1?}.", num_solutions, plural, elapsed_time);
+        }
+    }
+
+    num_solutions
+}
+
+// report why the search finished, once all its solutions have been printed
+fn print_search_end(num_solutions: usize, aborted: Option<AbortReason>) {
+    if num_solutions == 0 && aborted.is_none() {
+        println!("This Sudoku is unsolvable!");
+    }
+    print_aborted(aborted);
+}
+
+fn exit_code(err: &SudokuError) -> i32 {
+    match err {
+        SudokuError::InvalidDimension(_) => 2,
+        SudokuError::InvalidBlockShape { .. } => 8,
+        SudokuError::TooManyEntries { .. } => 3,
+        SudokuError::ValueOutOfRange { .. } => 4,
+        SudokuError::NonSquareEntryCount(_) => 5,
+        SudokuError::WrongEntryCount { .. } => 9,
+        SudokuError::WrongRegionSize { .. } => 10,
+        SudokuError::DisconnectedRegion(_) => 11,
+        SudokuError::UnparsableToken { .. } => 6,
+        SudokuError::MalformedCandidateGrid { .. } => 7,
+        SudokuError::ClueTargetNotReached { .. } => 12
+    }
+}
+
+// generate a puzzle and print it
+fn generate(args: &[String]) {
+    let mut options = GeneratorOptions::default();
+    if let Some(size) = parse_switch(args, SIZE_STR) {
+        options.size = size;
+    }
+    options.clues = parse_switch(args, CLUES_STR);
+    if let Some(symmetry) = parse_switch(args, SYMMETRY_STR) {
+        options.symmetry = symmetry;
+    }
+    if let Some(variant) = parse_switch(args, VARIANT_STR) {
+        options.variant = variant;
+    }
+    if let Some(seed) = parse_switch(args, SEED_STR) {
+        options.seed = seed;
+    }
+
+    match generator::generate(&options) {
+        Ok(puzzle) => println!("{}", puzzle),
+        Err(err) => {
+            println!("{}", err);
+            process::exit(exit_code(&err));
+        }
+    }
+}
+
+// report whether a Sudoku is minimal, and if not which of its clues are redundant
+fn analyze(args: &[String]) {
+    let sudoku = read_sudoku(args, FILENAME_PARAM + 1);
+    println!("Sudoku ({}/{}) is:\n{}\n",
+             sudoku.num_completed_cells(),
+             sudoku.num_cells(),
+             sudoku);
+
+    let analysis = analysis::analyze(&sudoku);
+    match analysis.uniqueness {
+        Uniqueness::None => println!("It has no solution, so it is not minimal."),
+        Uniqueness::Multiple(..) => println!("It has more than one solution, so it is not minimal."),
+        Uniqueness::Unique(_) if analysis.is_minimal() => {
+            println!("It has a unique solution, and is minimal: removing any clue would make it ambiguous.");
+        }
+        Uniqueness::Unique(_) => {
+            println!("It has a unique solution, but is not minimal. \
+                      Each of these {} clues could be removed on its own:",
+                     analysis.redundant_clues.len());
+            for (row, col, val) in &analysis.redundant_clues {
+                println!("row {}, col {}: {}", row + 1, col + 1, val);
+            }
+        }
+    }
+}
+
+// the parameters given after a subcommand, other than switches and their values
+fn subcommand_params(args: &[String]) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut args = args.iter().skip(FILENAME_PARAM + 1);
+    while let Some(arg) = args.next() {
+        if VALUE_SWITCHES.contains(&arg.as_str()) {
+            args.next();
+        } else if !arg.starts_with("--") {
+            params.push(arg.as_str());
+        }
+    }
+    params
+}
+
+// the puzzle files in a directory and its subdirectories, in order
+fn puzzle_files(dir: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+        Err(_) => {
+            println!("Could not read directory {}", dir.display());
+            Vec::new()
+        }
+    };
+    entries.sort();
 
-            println!("\nFound {} solution{} in {:.1?}:", num_solutions, plural, elapsed_time);
-            let mut count = 1;
-            for solution in solutions {
-                if num_solutions > 1 {
-                    println!("{}:", count);
-                    count += 1;
+    let mut files = Vec::new();
+    for entry in entries {
+        if entry.is_dir() {
+            files.extend(puzzle_files(&entry));
+        } else if entry.extension() == Some(OsStr::new("txt")) {
+            files.push(entry);
+        }
+    }
+    files
+}
+
+fn grade_description(grade: &Grade) -> String {
+    let steps = format!("{} step{}", grade.steps.len(), if grade.steps.len() == 1 { "" } else { "s" });
+    let hardest = match grade.hardest_technique {
+        Some(technique) => format!("hardest technique: {}", technique),
+        None => String::from("no techniques apply")
+    };
+    match &grade.guessing {
+        None => format!("{} (score {}, {}, {})", grade.level, grade.score, steps, hardest),
+        Some(guessing) if guessing.aborted.is_some() => {
+            format!("{} (requires guessing, but the search {} after {} nodes)",
+                    grade.level, guessing.aborted.map_or(String::new(), |reason| reason.to_string()),
+                    guessing.stats.nodes)
+        }
+        Some(guessing) if guessing.solutions == 0 => String::from("Unsolvable"),
+        Some(guessing) if guessing.solutions > 1 => String::from("Has more than one solution"),
+        Some(guessing) => {
+            format!("{} (score {}, requires guessing after {}, {}; search visited {} nodes, {} backtracks)",
+                    grade.level, grade.score, steps, hardest,
+                    guessing.stats.nodes, guessing.stats.backtracks)
+        }
+    }
+}
+
+// grade each puzzle file given, or in the directories given
+fn grade(args: &[String]) {
+    let params = subcommand_params(args);
+    if params.is_empty() {
+        println!("{}", USAGE_STR);
+        process::exit(1);
+    }
+    let options = solve_options(args);
+
+    for param in params {
+        let path = Path::new(param);
+        let files = if path.is_dir() { puzzle_files(path) } else { vec![path.to_path_buf()] };
+        for file in files {
+            let sudoku = fs::read_to_string(&file).map_err(|err| err.to_string())
+                                                  .and_then(|str| parse_sudoku(args, &str).map_err(|err| err.to_string()));
+            match sudoku {
+                Ok(sudoku) if !sudoku.is_consistent() => println!("{}: Unsolvable", file.display()),
+                Ok(sudoku) => {
+                    let grade = grading::grade_with(&sudoku, &options);
+                    println!("{}: {}", file.display(), grade_description(&grade));
                 }
-                println!("{}", solution);
+                Err(err) => println!("{}: {}", file.display(), err)
             }
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    match args.get(FILENAME_PARAM).map(|arg| arg.as_str()) {
+        Some(GENERATE_STR) => return generate(&args),
+        Some(ANALYZE_STR) => return analyze(&args),
+        Some(GRADE_STR) => return grade(&args),
+        _ => ()
+    }
+
+    let sudoku = read_sudoku(&args, FILENAME_PARAM);
+
+    let options = solve_options(&args);
+
+    // in counting mode, just print the number of solutions
+    if has_switch(&args, COUNT_STR) {
+        if sudoku.is_consistent() {
+            let outcome = solver::count_solutions_with(&sudoku, None, &options);
+            println!("{}", outcome.count);
+            print_aborted(outcome.aborted);
+            print_stats(&args, &outcome.stats);
         } else {
-            println!("This Sudoku is unsolvable!");
+            println!("0");
+        }
+        return;
+    }
+
+    println!("Initial Sudoku ({}/{}) is:\n{}",
+             sudoku.num_completed_cells(),
+             sudoku.num_cells(),
+             sudoku);
+
+    if sudoku.is_consistent() {
+        let start_time = Instant::now();
+
+        if options.threads > 1 {
+            // the search is split between threads, so the solutions arrive all at once
+            let mut options = options;
+            options.limit = if terminate_on_first(&args) { SolveLimit::First } else { SolveLimit::All };
+            let (solutions, stats, aborted) = match solver::solve_with(&sudoku, options) {
+                SolveOutcome::Completed { solutions, stats } => (solutions, stats, None),
+                SolveOutcome::Aborted { reason, solutions, partial_stats } => (solutions, partial_stats, Some(reason))
+            };
+            let num_solutions = print_solutions(&args, solutions.into_iter(), start_time);
+            print_search_end(num_solutions, aborted);
+            print_stats(&args, &stats);
+        } else {
+            // whether the search gave up is only known once it stops
+            let mut solutions = solver::solutions_with(&sudoku, &options);
+            let num_solutions = print_solutions(&args, solutions.by_ref(), start_time);
+            print_search_end(num_solutions, solutions.aborted());
+            print_stats(&args, solutions.stats());
         }
     } else {
         println!("Sudoku contains repeated numbers in rows, columns or blocks.");
     }
-}
\ No newline at end of file
+}
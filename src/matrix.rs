@@ -2,7 +2,7 @@ use std::ops::Range;
 
 use core::iter::Iterator as StdIterator;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Matrix<T: Clone> {
     elements: Vec<T>,
     num_rows: usize,
@@ -14,13 +14,12 @@ impl<T: Clone> Matrix<T> {
     pub fn new(default: T, num_rows: usize, num_cols: usize) -> Self {
         let num_elements = num_rows * num_cols;
         let elements = vec![default; num_elements];
-        let mat = Self {
+        Self {
             elements,
             num_rows,
             num_cols,
             num_elements
-        };
-        mat
+        }
     }
 
     fn element_index(&self, row: usize, col: usize) -> usize {
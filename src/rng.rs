@@ -0,0 +1,61 @@
+// A small pseudo-random number generator (SplitMix64), so that the random choices
+// made by the solver are reproducible from a seed, on any platform.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // a number in the range 0..n, which must not be empty
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "Cannot choose a number below 0");
+        ((u128::from(self.next_u64()) * n as u128) >> 64) as usize
+    }
+
+    // shuffle a slice into a random order (Fisher-Yates)
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn test_seeded() {
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+        let first: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..10).map(|_| b.next_u64()).collect::<Vec<u64>>());
+        assert_ne!(first[0], Rng::new(43).next_u64());
+    }
+
+    #[test]
+    fn test_below_and_shuffle() {
+        let mut rng = Rng::new(7);
+        assert!((0..1000).all(|_| rng.below(9) < 9));
+
+        let mut items: Vec<usize> = (0..20).collect();
+        rng.shuffle(&mut items);
+        assert_ne!((0..20).collect::<Vec<usize>>(), items);
+        items.sort();
+        assert_eq!((0..20).collect::<Vec<usize>>(), items);
+    }
+}
@@ -1,7 +1,257 @@
-use super::dlx::DLX;
-use super::Sudoku;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
 
-pub fn solve(sudoku: &Sudoku, terminate_on_first: bool) -> Vec<Sudoku> {
+use super::dlx::{DLX, MinRemainingValues, RandomMinRemainingValues, SearchLimits, Solutions};
+use super::rng::Rng;
+use super::{CandidateGrid, Sudoku};
+
+pub use super::dlx::{AbortReason, ColumnChooser, SolveLimit};
+pub use super::stats::SearchStats;
+
+// options controlling how much effort is put into solving a Sudoku; setting the
+// cancel flag (e.g., from another thread) stops the search as soon as possible,
+// with more than one thread the search is split between them, the chooser is
+// the heuristic for which constraint to branch on next, and the forbidden values
+// (e.g., ruled out by pencil marks) cannot be placed in their cells, given as
+// (row, col, val), where those outside the grid are ignored
+#[derive(Clone, Debug)]
+pub struct SolveOptions {
+    pub limit: SolveLimit,
+    pub timeout: Option<Duration>,
+    pub max_nodes: Option<u64>,
+    pub cancel: Arc<AtomicBool>,
+    pub threads: usize,
+    pub chooser: Box<dyn ColumnChooser>,
+    pub forbidden: Vec<(usize, usize, usize)>
+}
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        Self {
+            limit: SolveLimit::First,
+            timeout: None,
+            max_nodes: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+            threads: 1,
+            chooser: Box::new(MinRemainingValues),
+            forbidden: Vec::new()
+        }
+    }
+}
+
+impl SolveOptions {
+    // set up the matrix for a Sudoku to be searched according to the options
+    fn configure(&self, mat: &mut DLX, sudoku: &Sudoku) {
+        mat.set_limits(SearchLimits {
+            timeout: self.timeout,
+            max_nodes: self.max_nodes,
+            cancel: Some(Arc::clone(&self.cancel))
+        });
+        mat.set_chooser(self.chooser.clone());
+        remove_rows_for_forbidden_values(mat, sudoku, &self.forbidden);
+    }
+}
+
+// the result of a search that may have given up before it was complete,
+// in which case any solutions found before it did so are still returned
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolveOutcome {
+    Completed { solutions: Vec<Sudoku>, stats: SearchStats },
+    Aborted { reason: AbortReason, solutions: Vec<Sudoku>, partial_stats: SearchStats }
+}
+
+// the result of counting solutions, with the count so far if the search gave up
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CountOutcome {
+    pub count: u64,
+    pub aborted: Option<AbortReason>,
+    pub stats: SearchStats
+}
+
+// whether a Sudoku has no solution, exactly one, or more than one (in which case
+// two different solutions are given as witnesses)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Uniqueness {
+    None,
+    Unique(Sudoku),
+    Multiple(Sudoku, Sudoku)
+}
+
+// whether the candidates of a grid still allow a solution of its puzzle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CandidateCheck {
+    // a solution in which every value was a candidate for its cell
+    Consistent(Sudoku),
+    // the values in the grid have a solution, but the candidates exclude it:
+    // these are the values of the solution that were eliminated, as (row, col, val)
+    Excluded { solution: Sudoku, eliminated: Vec<(usize, usize, usize)> },
+    // the values in the grid have no solution, whatever the candidates
+    Unsolvable
+}
+
+// the solutions of a Sudoku, found lazily one at a time
+pub struct SudokuSolutions {
+    sudoku: Sudoku,
+    solutions: Solutions<DLX>
+}
+
+impl SudokuSolutions {
+    // statistics for the search so far
+    pub fn stats(&self) -> &SearchStats {
+        self.solutions.stats()
+    }
+
+    // the reason the search gave up, if it did
+    pub fn aborted(&self) -> Option<AbortReason> {
+        self.solutions.aborted()
+    }
+}
+
+impl Iterator for SudokuSolutions {
+    type Item = Sudoku;
+
+    fn next(&mut self) -> Option<Sudoku> {
+        // convert the solutions into completed Sudoku(s) as they are found
+        self.solutions.next()
+                      .map(|solution| complete_sudoku(&self.sudoku, &solution))
+    }
+}
+
+pub fn solve(sudoku: &Sudoku, limit: SolveLimit) -> Vec<Sudoku> {
+    solve_with_stats(sudoku, limit).0
+}
+
+pub fn solve_with_stats(sudoku: &Sudoku, limit: SolveLimit) -> (Vec<Sudoku>, SearchStats) {
+    let mut solutions = solutions(sudoku);
+    let completed_sudokus = solutions.by_ref().take(limit.max_solutions()).collect();
+    (completed_sudokus, solutions.stats().clone())
+}
+
+// check whether a Sudoku has a unique solution, stopping at the second one found
+pub fn uniqueness(sudoku: &Sudoku) -> Uniqueness {
+    let mut solutions = solve(sudoku, SolveLimit::UpTo(2)).into_iter();
+    match (solutions.next(), solutions.next()) {
+        (Some(first), Some(second)) => Uniqueness::Multiple(first, second),
+        (Some(solution), None) => Uniqueness::Unique(solution),
+        _ => Uniqueness::None
+    }
+}
+
+pub fn solve_with(sudoku: &Sudoku, options: SolveOptions) -> SolveOutcome {
+    let (completed_sudokus, stats, aborted) = if options.threads > 1 {
+        let mut mat = build_matrix(sudoku);
+        options.configure(&mut mat, sudoku);
+        let completed_sudokus = mat.find_solutions_parallel(options.limit, options.threads)
+                                   .iter()
+                                   .map(|solution| complete_sudoku(sudoku, solution))
+                                   .collect();
+        (completed_sudokus, mat.stats().clone(), mat.aborted())
+    } else {
+        let mut solutions = solutions_with(sudoku, &options);
+        let completed_sudokus = solutions.by_ref().take(options.limit.max_solutions()).collect();
+        (completed_sudokus, solutions.stats().clone(), solutions.aborted())
+    };
+
+    match aborted {
+        Some(reason) => SolveOutcome::Aborted { reason, solutions: completed_sudokus, partial_stats: stats },
+        None => SolveOutcome::Completed { solutions: completed_sudokus, stats }
+    }
+}
+
+// solve the puzzle in a grid, allowing only the candidates left for each cell
+pub fn solve_candidates(grid: &CandidateGrid, limit: SolveLimit) -> Vec<Sudoku> {
+    let options = SolveOptions { limit, forbidden: grid.eliminated_candidates(), ..Default::default() };
+    match solve_with(&grid.to_sudoku(), options) {
+        SolveOutcome::Completed { solutions, .. } | SolveOutcome::Aborted { solutions, .. } => solutions
+    }
+}
+
+// Check whether the candidates of a grid, e.g. a player's pencil marks, still
+// allow a solution of its puzzle. If they do not, and the puzzle has more than
+// one solution, the solution they are compared with is the first one found.
+pub fn check_candidates(grid: &CandidateGrid) -> CandidateCheck {
+    if let Some(solution) = solve_candidates(grid, SolveLimit::First).pop() {
+        return CandidateCheck::Consistent(solution);
+    }
+    match solve(&grid.to_sudoku(), SolveLimit::First).pop() {
+        Some(solution) => {
+            let mut eliminated: Vec<(usize, usize, usize)> = solution.completed_cells()
+                .into_iter()
+                .filter(|(row, col, val)| grid.cell_value(*row, *col).is_none() && !grid.is_candidate(*row, *col, *val))
+                .collect();
+            eliminated.sort_unstable();
+            CandidateCheck::Excluded { solution, eliminated }
+        }
+        None => CandidateCheck::Unsolvable
+    }
+}
+
+// lazily find the solutions of a Sudoku, one at a time (on a single thread)
+pub fn solutions(sudoku: &Sudoku) -> SudokuSolutions {
+    SudokuSolutions {
+        sudoku: sudoku.clone(),
+        solutions: build_matrix(sudoku).into_solutions()
+    }
+}
+
+// as for solutions, but stopping early if the timeout, node budget or cancel
+// flag of the options say so, and using their chooser (the solution limit and
+// threads are not applied)
+pub fn solutions_with(sudoku: &Sudoku, options: &SolveOptions) -> SudokuSolutions {
+    let mut mat = build_matrix(sudoku);
+    options.configure(&mut mat, sudoku);
+    SudokuSolutions {
+        sudoku: sudoku.clone(),
+        solutions: mat.into_solutions()
+    }
+}
+
+// Find a solution of a Sudoku chosen at random, by exploring the possible values
+// of each cell, and breaking ties between the cells to fill in, in an order
+// shuffled according to a seed. The same seed always gives the same solution.
+pub fn random_solution(sudoku: &Sudoku, seed: u64) -> Option<Sudoku> {
+    let mut rng = Rng::new(seed);
+    let (num_rows, num_cols) = matrix_dimensions(sudoku);
+    let mut mat = DLX::new(num_rows, num_cols);
+    populate_matrix(&mut mat, sudoku);
+    mat.shuffle_rows(&mut rng);
+    eliminate_rows_for_completed_cells(&mut mat, sudoku);
+    mat.set_chooser(Box::new(RandomMinRemainingValues::new(rng.next_u64())));
+
+    let solution = mat.solutions().next();
+    solution.map(|solution| complete_sudoku(sudoku, &solution))
+}
+
+// count the solutions of a Sudoku, up to an optional limit
+pub fn count_solutions(sudoku: &Sudoku, limit: Option<u64>) -> u64 {
+    count_solutions_with_stats(sudoku, limit).0
+}
+
+pub fn count_solutions_with_stats(sudoku: &Sudoku, limit: Option<u64>) -> (u64, SearchStats) {
+    let mut mat = build_matrix(sudoku);
+    let count = mat.count_solutions(limit);
+    (count, mat.stats().clone())
+}
+
+// count the solutions of a Sudoku, up to an optional limit, stopping early if
+// the timeout, node budget or cancel flag of the options say so
+pub fn count_solutions_with(sudoku: &Sudoku, limit: Option<u64>, options: &SolveOptions) -> CountOutcome {
+    let mut mat = build_matrix(sudoku);
+    options.configure(&mut mat, sudoku);
+    let count = if options.threads > 1 {
+        mat.count_solutions_parallel(limit, options.threads)
+    } else {
+        mat.count_solutions(limit)
+    };
+    CountOutcome {
+        count,
+        aborted: mat.aborted(),
+        stats: mat.stats().clone()
+    }
+}
+
+fn build_matrix(sudoku: &Sudoku) -> DLX {
     // initialise the matrix
     let (num_rows, num_cols) = matrix_dimensions(sudoku);
     let mut mat = DLX::new(num_rows, num_cols);
@@ -11,23 +261,12 @@ pub fn solve(sudoku: &Sudoku, terminate_on_first: bool) -> Vec<Sudoku> {
     populate_matrix(&mut mat, sudoku);
     eliminate_rows_for_completed_cells(&mut mat, sudoku);
 
-    // find the solution(s)
-    let solutions = mat.find_solutions(terminate_on_first);
-
-    // convert the solutions into completed Sudoku(s)
-    let mut completed_sudokus = Vec::new();
-    for solution in solutions {
-        let completed_solution = complete_sudoku(&sudoku, &solution);
-        completed_sudokus.push(completed_solution);
-    }
-
-    // return the result
-    completed_sudokus
+    mat
 }
 
 fn matrix_dimensions(sudoku: &Sudoku) -> (usize, usize) {
     let num_rows = sudoku.dimension().pow(3);
-    let num_cols = 4 * sudoku.dimension().pow(2);
+    let num_cols = 4 * sudoku.dimension().pow(2) + sudoku.num_diagonals() * sudoku.dimension();
     (num_rows, num_cols)
 }
 
@@ -40,25 +279,24 @@ fn populate_matrix(matrix: &mut DLX, sudoku: &Sudoku) {
                 // current row in the matrix
                 let mat_row = matrix_row_for_cell_value(sudoku, row, col, val);
 
-                // cells
-                let mat_col = col + row * sudoku.dimension();
-                matrix.set_element(mat_row, mat_col);
-
-                // rows
-                let mat_col = region_width +
+                // the cell, row, col and block constraints satisfied by this row,
+                // where the blocks of a jigsaw Sudoku are its regions
+                let cell_col = col + row * sudoku.dimension();
+                let row_col = region_width +
                               (row * sudoku.dimension()) + (val - 1);
-                matrix.set_element(mat_row, mat_col);
-
-                // cols
-                let mat_col = (region_width * 2) +
+                let col_col = (region_width * 2) +
                               (col * sudoku.dimension()) + (val - 1);
-                matrix.set_element(mat_row, mat_col);
+                let block_col = (region_width * 3) +
+                                (sudoku.block_no(row, col) * sudoku.dimension()) +
+                                (val - 1);
+                let mut cols = vec![cell_col, row_col, col_col, block_col];
+
+                // and the constraints of any diagonals the cell is on
+                for diagonal in sudoku.diagonal_nos(row, col) {
+                    cols.push((region_width * 4) + (diagonal * sudoku.dimension()) + (val - 1));
+                }
 
-                // blocks
-                let mat_col = (region_width * 3) +
-                              (sudoku.block_no(row, col) * sudoku.dimension()) +
-                              (val - 1);
-                matrix.set_element(mat_row, mat_col);
+                matrix.add_row(mat_row, &cols);
             }
         }
     }
@@ -71,6 +309,20 @@ fn eliminate_rows_for_completed_cells(matrix: &mut DLX, sudoku: &Sudoku) {
     }
 }
 
+// remove the rows for values that cannot be placed in their cells (which has no
+// effect on cells that are already completed)
+fn remove_rows_for_forbidden_values(matrix: &mut DLX, sudoku: &Sudoku, forbidden: &[(usize, usize, usize)]) {
+    let dimension = sudoku.dimension();
+    for (row, col, val) in forbidden {
+        // a cell or value outside the grid could never be placed anyway
+        if *row >= dimension || *col >= dimension || *val == 0 || *val > dimension {
+            continue;
+        }
+        let mat_row = matrix_row_for_cell_value(sudoku, *row, *col, *val);
+        matrix.remove_row(mat_row);
+    }
+}
+
 // take a row, col, and value of a Sudoku cell and find the corresponding matrix row number
 fn matrix_row_for_cell_value(sudoku: &Sudoku, row: usize, col: usize, val: usize) -> usize {
     (val - 1) + (sudoku.dimension() * (col + row * sudoku.dimension()))
@@ -87,7 +339,7 @@ fn cell_value_for_matrix_row(sudoku: &Sudoku, mat_row: usize) -> (usize, usize,
 }
 
 // complete a sudoku given a solution returned by the matrix
-fn complete_sudoku(sudoku: &Sudoku, solution: &Vec<usize>) -> Sudoku {
+fn complete_sudoku(sudoku: &Sudoku, solution: &[usize]) -> Sudoku {
     let mut sudoku = sudoku.clone();
     for soln_row in solution {
         let (row, col, val) = cell_value_for_matrix_row(&sudoku, *soln_row);
@@ -96,9 +348,11 @@ fn complete_sudoku(sudoku: &Sudoku, solution: &Vec<usize>) -> Sudoku {
     sudoku
 }
 
+#[cfg(test)]
 mod tests {
-    use super::Sudoku;
-    use super::DLX;
+    use super::{CandidateGrid, Sudoku};
+    use super::super::dlx::{FirstColumn, RandomMinRemainingValues};
+    use super::super::Variant;
     use super::super::solver; // TODO: is there a better way to state this
 
     #[test]
@@ -113,6 +367,222 @@ mod tests {
         assert_eq!((3, 3, 4), solver::cell_value_for_matrix_row(sud, 63), "row 63");
     }
 
+    #[test]
+    fn test_solutions() {
+        let sud: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        let mut solutions = solver::solutions(&sud);
+
+        let solution = solutions.next().unwrap();
+        assert!(solution.is_completed());
+        assert!(solution.is_consistent());
+        assert_eq!(Some(3), solution.cell_value(0, 0));
+        assert!(solutions.next().is_none());
+
+        assert_eq!(288, solver::solutions(&Sudoku::new(4)).count());
+        assert_eq!(5, solver::solutions(&Sudoku::new(9)).take(5).count());
+    }
+
+    #[test]
+    fn test_solve() {
+        let sud = Sudoku::new(4);
+        assert_eq!(1, solver::solve(&sud, solver::SolveLimit::First).len());
+        assert_eq!(10, solver::solve(&sud, solver::SolveLimit::UpTo(10)).len());
+        assert_eq!(288, solver::solve(&sud, solver::SolveLimit::All).len());
+    }
+
+    #[test]
+    fn test_solve_with_stats() {
+        let sud: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        let (solutions, stats) = solver::solve_with_stats(&sud, solver::SolveLimit::All);
+        assert_eq!(1, solutions.len());
+        assert_eq!(8, stats.max_depth);
+        assert!(stats.nodes > 8);
+
+        let (count, stats) = solver::count_solutions_with_stats(&Sudoku::new(4), None);
+        assert_eq!(288, count);
+        assert_eq!(16, stats.max_depth);
+    }
+
+    #[test]
+    fn test_solve_with() {
+        let sud = Sudoku::new(4);
+        let options = solver::SolveOptions { limit: solver::SolveLimit::All, ..Default::default() };
+        match solver::solve_with(&sud, options) {
+            solver::SolveOutcome::Completed { solutions, .. } => assert_eq!(288, solutions.len()),
+            _ => panic!("expected the search to complete")
+        }
+
+        let options = solver::SolveOptions {
+            limit: solver::SolveLimit::All,
+            max_nodes: Some(100),
+            ..Default::default()
+        };
+        match solver::solve_with(&sud, options) {
+            solver::SolveOutcome::Aborted { reason, solutions, partial_stats } => {
+                assert_eq!(solver::AbortReason::NodeLimit, reason);
+                assert!(!solutions.is_empty() && solutions.len() < 288);
+                assert_eq!(100, partial_stats.nodes);
+            }
+            _ => panic!("expected the search to be aborted")
+        }
+
+        let options = solver::SolveOptions::default();
+        options.cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+        let outcome = solver::count_solutions_with(&sud, None, &options);
+        assert_eq!(Some(solver::AbortReason::Cancelled), outcome.aborted);
+    }
+
+    #[test]
+    fn test_solve_with_threads() {
+        let sud = Sudoku::new(4);
+        let options = solver::SolveOptions { limit: solver::SolveLimit::All, threads: 4, ..Default::default() };
+        match solver::solve_with(&sud, options) {
+            solver::SolveOutcome::Completed { solutions, .. } => {
+                assert_eq!(solver::solve(&sud, solver::SolveLimit::All), solutions);
+            }
+            _ => panic!("expected the search to complete")
+        }
+
+        let options = solver::SolveOptions { threads: 3, ..Default::default() };
+        assert_eq!(288, solver::count_solutions_with(&sud, None, &options).count);
+        assert_eq!(10, solver::count_solutions_with(&sud, Some(10), &options).count);
+    }
+
+    #[test]
+    fn test_solve_with_chooser() {
+        let sud: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        let options = solver::SolveOptions { chooser: Box::new(FirstColumn), ..Default::default() };
+        let outcome = solver::count_solutions_with(&sud, None, &options);
+        assert_eq!(1, outcome.count);
+        assert_eq!(solver::solve(&sud, solver::SolveLimit::All),
+                   solver::solutions_with(&sud, &options).collect::<Vec<_>>());
+
+        let options = solver::SolveOptions { chooser: Box::new(RandomMinRemainingValues::new(3)), ..Default::default() };
+        assert_eq!(288, solver::count_solutions_with(&Sudoku::new(4), None, &options).count);
+    }
+
+    #[test]
+    fn test_solve_with_forbidden() {
+        // with 1, 2 and 3 forbidden in the first cell, it can only hold 4
+        let sud = Sudoku::new(4);
+        let options = solver::SolveOptions {
+            forbidden: vec![(0, 0, 1), (0, 0, 2), (0, 0, 3)],
+            ..Default::default()
+        };
+        assert_eq!(72, solver::count_solutions_with(&sud, None, &options).count);
+        assert!(solver::solutions_with(&sud, &options).all(|solution| solution.cell_value(0, 0) == Some(4)));
+
+        // a forbidden value that has already been placed stays put
+        let sud: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        let options = solver::SolveOptions { forbidden: vec![(0, 0, 3)], ..Default::default() };
+        assert_eq!(1, solver::count_solutions_with(&sud, None, &options).count);
+        let options = solver::SolveOptions { forbidden: vec![(0, 1, 2)], ..Default::default() };
+        assert_eq!(0, solver::count_solutions_with(&sud, None, &options).count);
+
+        // triples outside the grid are ignored
+        let sud = Sudoku::new(4);
+        let options = solver::SolveOptions {
+            forbidden: vec![(0, 0, 5), (0, 0, 0), (9, 0, 1), (0, 4, 1)],
+            ..Default::default()
+        };
+        assert_eq!(288, solver::count_solutions_with(&sud, None, &options).count);
+    }
+
+    #[test]
+    fn test_solve_jigsaw() {
+        let sud: Sudoku = ". . . . . 3 2 . . 2 4 . . . . .\nregions: AAAB CABB CCDB CDDD".parse().unwrap();
+        let solutions = solver::solve(&sud, solver::SolveLimit::All);
+        let expected: Sudoku = "2 4 1 3 1 3 2 4 3 2 4 1 4 1 3 2\nregions: AAAB CABB CCDB CDDD".parse().unwrap();
+        assert_eq!(vec![expected], solutions);
+
+        // the same clues have other solutions with the usual blocks
+        let sud: Sudoku = ". . . . . 3 2 . . 2 4 . . . . .".parse().unwrap();
+        assert_eq!(2, solver::count_solutions(&sud, None));
+    }
+
+    #[test]
+    fn test_solve_x() {
+        let mut sud = Sudoku::new(4);
+        sud.set_variant(Variant::X);
+        let solutions = solver::solve(&sud, solver::SolveLimit::All);
+        assert_eq!(48, solutions.len());
+        assert!(solutions.iter().all(|solution| solution.is_consistent()));
+
+        // the diagonals rule out most of the solutions of a regular Sudoku
+        let mut sud: Sudoku = "1 . . . . . . . . . . . . . . 2".parse().unwrap();
+        assert_eq!(18, solver::count_solutions(&sud, None));
+        sud.set_variant(Variant::X);
+        assert_eq!(4, solver::count_solutions(&sud, None));
+    }
+
+    #[test]
+    fn test_check_candidates() {
+        let sud: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        let solution = solver::solve(&sud, solver::SolveLimit::First).remove(0);
+        let mut grid = CandidateGrid::new(&sud);
+        assert_eq!(vec![solution.clone()], solver::solve_candidates(&grid, solver::SolveLimit::All));
+        assert_eq!(solver::CandidateCheck::Consistent(solution.clone()), solver::check_candidates(&grid));
+
+        grid.eliminate_candidate(0, 1, 2);
+        assert!(solver::solve_candidates(&grid, solver::SolveLimit::All).is_empty());
+        assert_eq!(solver::CandidateCheck::Excluded { solution, eliminated: vec![(0, 1, 2)] },
+                   solver::check_candidates(&grid));
+
+        let grid = CandidateGrid::new(&"3.4. ..2. 1... .4.3".parse().unwrap());
+        assert_eq!(solver::CandidateCheck::Unsolvable, solver::check_candidates(&grid));
+
+        // the marks narrow down the solutions of a puzzle with more than one
+        let mut grid = CandidateGrid::new(&Sudoku::new(4));
+        grid.eliminate_candidate(0, 0, 1);
+        let solutions = solver::solve_candidates(&grid, solver::SolveLimit::All);
+        assert_eq!(216, solutions.len());
+        assert!(solutions.iter().all(|solution| solution.cell_value(0, 0) != Some(1)));
+    }
+
+    #[test]
+    fn test_random_solution() {
+        let sud = Sudoku::new(9);
+        let solution = solver::random_solution(&sud, 42).unwrap();
+        assert!(solution.is_completed() && solution.is_consistent());
+        assert_eq!(Some(solution.clone()), solver::random_solution(&sud, 42));
+        assert_ne!(Some(solution), solver::random_solution(&sud, 43));
+
+        // the clues are kept, and a unique solution is always found
+        let sud: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        let expected = solver::solve(&sud, solver::SolveLimit::First);
+        assert_eq!(expected[0], solver::random_solution(&sud, 7).unwrap());
+
+        let sud: Sudoku = "3.4. ..2. 1... .4.3".parse().unwrap();
+        assert_eq!(None, solver::random_solution(&sud, 7));
+    }
+
+    #[test]
+    fn test_uniqueness() {
+        let sud: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        assert!(matches!(solver::uniqueness(&sud), solver::Uniqueness::Unique(_)));
+
+        let sud: Sudoku = "3... .... .... ....".parse().unwrap();
+        match solver::uniqueness(&sud) {
+            solver::Uniqueness::Multiple(first, second) => {
+                assert!(first.is_completed() && second.is_completed());
+                assert_ne!(first, second);
+                assert!(!first.differences(&second).is_empty());
+            }
+            _ => panic!("expected multiple solutions")
+        }
+
+        let sud: Sudoku = "3.4. ..2. 1... .4.3".parse().unwrap();
+        assert_eq!(solver::Uniqueness::None, solver::uniqueness(&sud));
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let sud: Sudoku = "3.4. .1.2 .4.3 2.1.".parse().unwrap();
+        assert_eq!(1, solver::count_solutions(&sud, None));
+        assert_eq!(288, solver::count_solutions(&Sudoku::new(4), None));
+        assert_eq!(100, solver::count_solutions(&Sudoku::new(9), Some(100)));
+    }
+
     #[test]
     fn test_matrix_row_for_cell_value() {
         let sud = &Sudoku::new(4);
@@ -0,0 +1,182 @@
+use std::fmt;
+use std::time::Duration;
+
+// Measures of the effort expended by a search through a DLX matrix.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    // the number of nodes of the search tree visited
+    pub nodes: u64,
+    // the number of rows chosen and then undone on the way back up the tree
+    pub backtracks: u64,
+    // the deepest level of the search tree reached
+    pub max_depth: usize,
+    // the number of vertices covered and uncovered
+    pub covers: u64,
+    pub uncovers: u64,
+    // for each depth, the number of nodes with each branching factor, i.e.,
+    // branching[depth][factor] nodes at that depth had that many rows to try
+    // (while a search is suspended, there may be entries of zero beyond those)
+    pub branching: Vec<Vec<u64>>,
+    // the time spent searching
+    pub time: Duration
+}
+
+impl SearchStats {
+    pub fn record_node(&mut self, depth: usize, branching_factor: usize) {
+        self.nodes += 1;
+        if depth > self.max_depth {
+            self.max_depth = depth;
+        }
+
+        if self.branching.len() <= depth {
+            self.branching.resize(depth + 1, Vec::new());
+        }
+        let histogram = &mut self.branching[depth];
+        if histogram.len() <= branching_factor {
+            histogram.resize(branching_factor + 1, 0);
+        }
+        histogram[branching_factor] += 1;
+    }
+
+    // Make room in the histogram of branching factors for a search no deeper
+    // than max_depth whose nodes have at most max_factor rows to try, so that
+    // recording its nodes never needs to allocate.
+    pub(crate) fn reserve_branching(&mut self, max_depth: usize, max_factor: usize) {
+        if self.branching.len() <= max_depth {
+            self.branching.resize(max_depth + 1, Vec::new());
+        }
+        for histogram in self.branching.iter_mut() {
+            if histogram.len() <= max_factor {
+                histogram.resize(max_factor + 1, 0);
+            }
+        }
+    }
+
+    // remove the entries of the histogram beyond the deepest level reached and
+    // the largest branching factor at each depth
+    pub(crate) fn trim_branching(&mut self) {
+        let depths = if self.nodes == 0 { 0 } else { self.max_depth + 1 };
+        self.branching.truncate(depths);
+        for histogram in self.branching.iter_mut() {
+            let factors = histogram.iter().rposition(|count| *count > 0).map_or(0, |factor| factor + 1);
+            histogram.truncate(factors);
+        }
+    }
+
+    // add the statistics of a search of a subtree rooted at the given depth
+    // (the time is not added, since subtrees may be searched at the same time)
+    pub fn merge(&mut self, other: &SearchStats, depth: usize) {
+        self.nodes += other.nodes;
+        self.backtracks += other.backtracks;
+        self.covers += other.covers;
+        self.uncovers += other.uncovers;
+        if other.nodes > 0 && other.max_depth + depth > self.max_depth {
+            self.max_depth = other.max_depth + depth;
+        }
+
+        if self.branching.len() < other.branching.len() + depth {
+            self.branching.resize(other.branching.len() + depth, Vec::new());
+        }
+        for (other_depth, other_histogram) in other.branching.iter().enumerate() {
+            let histogram = &mut self.branching[other_depth + depth];
+            if histogram.len() < other_histogram.len() {
+                histogram.resize(other_histogram.len(), 0);
+            }
+            for (factor, count) in other_histogram.iter().enumerate() {
+                histogram[factor] += count;
+            }
+        }
+    }
+
+    // the average number of rows tried per node, over the nodes that had any to try
+    pub fn mean_branching_factor(&self) -> f64 {
+        let mut branches = 0;
+        let mut nodes = 0;
+        for histogram in &self.branching {
+            for (factor, count) in histogram.iter().enumerate().skip(1) {
+                branches += factor as u64 * count;
+                nodes += count;
+            }
+        }
+        if nodes == 0 {
+            0.0
+        } else {
+            branches as f64 / nodes as f64
+        }
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Nodes visited: {}", self.nodes)?;
+        writeln!(f, "Backtracks: {}", self.backtracks)?;
+        writeln!(f, "Max depth: {}", self.max_depth)?;
+        writeln!(f, "Covers: {}", self.covers)?;
+        writeln!(f, "Uncovers: {}", self.uncovers)?;
+        writeln!(f, "Mean branching factor: {:.2}", self.mean_branching_factor())?;
+        writeln!(f, "Search time: {:.1?}", self.time)?;
+        write!(f, "Branching factors by depth (factor x nodes):")?;
+        let depths = if self.nodes == 0 { 0 } else { self.max_depth + 1 };
+        for (depth, histogram) in self.branching.iter().enumerate().take(depths) {
+            write!(f, "\n{:>5}:", depth)?;
+            for (factor, count) in histogram.iter().enumerate() {
+                if *count > 0 {
+                    write!(f, " {}x{}", factor, count)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchStats;
+
+    #[test]
+    fn test_record_node() {
+        let mut stats = SearchStats::default();
+        stats.record_node(0, 2);
+        stats.record_node(1, 1);
+        stats.record_node(1, 0);
+        stats.record_node(1, 1);
+
+        assert_eq!(4, stats.nodes);
+        assert_eq!(1, stats.max_depth);
+        assert_eq!(vec![vec![0, 0, 1], vec![1, 2]], stats.branching);
+        assert!((stats.mean_branching_factor() - 4.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_reserve_branching() {
+        let mut stats = SearchStats::default();
+        stats.reserve_branching(3, 4);
+        assert_eq!(vec![vec![0; 5]; 4], stats.branching);
+        stats.record_node(0, 2);
+        stats.record_node(1, 1);
+        assert_eq!(5, stats.branching[1].len());
+
+        stats.trim_branching();
+        assert_eq!(vec![vec![0, 0, 1], vec![0, 1]], stats.branching);
+        stats.trim_branching();
+        assert_eq!(vec![vec![0, 0, 1], vec![0, 1]], stats.branching);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut stats = SearchStats::default();
+        stats.record_node(0, 2);
+
+        let mut other = SearchStats::default();
+        other.record_node(0, 1);
+        other.record_node(1, 0);
+        other.covers = 3;
+
+        stats.merge(&other, 1);
+        stats.merge(&other, 1);
+        assert_eq!(5, stats.nodes);
+        assert_eq!(2, stats.max_depth);
+        assert_eq!(6, stats.covers);
+        assert_eq!(vec![vec![0, 0, 1], vec![0, 2], vec![2]], stats.branching);
+    }
+}
@@ -1,103 +1,429 @@
-use std::cmp;
+use std::error;
 use std::fmt;
+use std::str::FromStr;
 
 use super::matrix::Matrix;
 use super::matrix::Iterator;
+use super::SudokuError;
 
-#[derive(Clone)]
+// The rules a Sudoku follows, besides each value appearing once in every row,
+// column and block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    Standard,
+    // each value also appears once in both of the main diagonals (Sudoku-X)
+    X
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Standard => write!(f, "standard"),
+            Variant::X => write!(f, "x")
+        }
+    }
+}
+
+// the error given when a variant cannot be parsed from its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseVariantError(String);
+
+impl fmt::Display for ParseVariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown variant: {} (expected standard or x)", self.0)
+    }
+}
+
+impl error::Error for ParseVariantError {}
+
+impl FromStr for Variant {
+    type Err = ParseVariantError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "standard" => Ok(Variant::Standard),
+            "x" => Ok(Variant::X),
+            _ => Err(ParseVariantError(str.to_string()))
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Sudoku {
     cells: Matrix<usize>,
     dimension: usize,
-    block_dimension: usize
+    block_height: usize,
+    block_width: usize,
+    // the region of each cell, row by row, in a jigsaw Sudoku whose blocks are
+    // irregular regions rather than rectangles
+    regions: Option<Vec<usize>>,
+    variant: Variant
 }
 
 impl Sudoku {
     const H_SEP_CHAR: char = '-';
     const V_SEP_CHAR: char = '|';
     const EMPTY_CELL_CHAR: char = '.';
+    const LEADING_DIAGONAL_CHAR: char = '\\';
+    const ANTI_DIAGONAL_CHAR: char = '/';
+    const BOTH_DIAGONALS_CHAR: char = 'X';
     const NEW_LINE_CHAR: char = '\n';
     const SPACE_CHAR: char = ' ';
+    const NUMBER_SEP_CHAR: char = '~';
     const EMPTY_CELL: usize = 0;
+    const REGIONS_HEADER: &'static str = "regions:";
+    const VARIANT_HEADER: &'static str = "variant:";
+    const REGION_LABELS: &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789@#";
+
+    pub const MAX_DIMENSION: usize = 64;
+
+    // create a Sudoku with the usual shape of block for its dimension (see
+    // default_block_shape)
+    pub fn try_new(dimension: usize) -> Result<Self, SudokuError> {
+        if dimension > Sudoku::MAX_DIMENSION {
+            return Err(SudokuError::InvalidDimension(dimension));
+        }
+        match Sudoku::default_block_shape(dimension) {
+            Some((block_height, block_width)) => Sudoku::try_with_block_shape(block_height, block_width),
+            None => Err(SudokuError::InvalidDimension(dimension))
+        }
+    }
 
     pub fn new(dimension: usize) -> Self {
-        // figure out the block dimension
-        let block_dimension = (dimension as f64).sqrt() as usize;
+        match Sudoku::try_new(dimension) {
+            Ok(sudoku) => sudoku,
+            Err(err) => panic!("{}", err)
+        }
+    }
 
-        // are the dimensions of this Sudoku valid?
-        if block_dimension.pow(2) != dimension {
-            // TODO: propagate this as an error
-            println!("Illegal Sudoku dimension: {}", dimension);
-            std::process::exit(1);
+    // create a Sudoku whose blocks have the given numbers of rows and columns,
+    // e.g. 2 by 3 for a 6x6 Sudoku
+    pub fn try_with_block_shape(block_height: usize, block_width: usize) -> Result<Self, SudokuError> {
+        let dimension = block_height * block_width;
+        if dimension == 0 || dimension > Sudoku::MAX_DIMENSION {
+            return Err(SudokuError::InvalidBlockShape { height: block_height, width: block_width });
         }
 
-        // instantiate the Sudoku
-        Self {
+        Ok(Self {
             cells: Matrix::new(0, dimension, dimension),
             dimension,
-            block_dimension
+            block_height,
+            block_width,
+            regions: None,
+            variant: Variant::Standard
+        })
+    }
+
+    pub fn with_block_shape(block_height: usize, block_width: usize) -> Self {
+        match Sudoku::try_with_block_shape(block_height, block_width) {
+            Ok(sudoku) => sudoku,
+            Err(err) => panic!("{}", err)
         }
     }
 
-    pub fn from(str: &str) -> Self {
-        let mut max_val = 0;
-        let mut entries: Vec<usize> = Vec::new();
+    // Create a jigsaw Sudoku, whose blocks are irregular regions. The region of
+    // each cell is given row by row, numbering the regions from 0. Each region
+    // must have as many cells as the dimension, and all its cells must be joined
+    // through cells that share a side with each other.
+    pub fn try_with_regions(regions: &[usize]) -> Result<Self, SudokuError> {
+        let dimension = (regions.len() as f64).sqrt() as usize;
+        if dimension.pow(2) != regions.len() {
+            return Err(SudokuError::NonSquareEntryCount(regions.len()));
+        }
+        if dimension == 0 || dimension > Sudoku::MAX_DIMENSION {
+            return Err(SudokuError::InvalidDimension(dimension));
+        }
+
+        // with every region below the dimension the right size, there is no
+        // room left for any others
+        let mut sizes = vec![0; dimension];
+        for &region in regions.iter().filter(|region| **region < dimension) {
+            sizes[region] += 1;
+        }
+        if let Some(region) = (0..dimension).find(|region| sizes[*region] != dimension) {
+            return Err(SudokuError::WrongRegionSize { region, size: sizes[region], expected: dimension });
+        }
+        if let Some(region) = (0..dimension).find(|region| !Sudoku::is_region_connected(regions, dimension, *region)) {
+            return Err(SudokuError::DisconnectedRegion(region));
+        }
+
+        // the blocks of the regular Sudoku of this size are only used to lay out
+        // the candidates of each cell
+        let (block_height, block_width) = Sudoku::default_block_shape(dimension).unwrap_or((1, dimension));
+        Ok(Self {
+            cells: Matrix::new(0, dimension, dimension),
+            dimension,
+            block_height,
+            block_width,
+            regions: Some(regions.to_vec()),
+            variant: Variant::Standard
+        })
+    }
+
+    pub fn with_regions(regions: &[usize]) -> Self {
+        match Sudoku::try_with_regions(regions) {
+            Ok(sudoku) => sudoku,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    // The shape of the blocks of a grid drawn with horizontal rules between
+    // the rows of blocks, and vertical lines between the columns of blocks: the
+    // rows between the first two rules, and the cells between the first two
+    // vertical lines of the first of those rows.
+    fn drawn_block_shape(str: &str) -> Option<(usize, usize)> {
+        let is_rule = |line: &str| {
+            let line = line.trim();
+            !line.is_empty() && line.chars().all(|c| c == Sudoku::H_SEP_CHAR)
+        };
+        let rows: Vec<&str> = str.lines()
+                                 .skip_while(|line| !is_rule(line))
+                                 .skip(1)
+                                 .take_while(|line| !is_rule(line))
+                                 .filter(|line| !line.trim().is_empty())
+                                 .collect();
+        let first_row = rows.first()?;
+        if first_row.matches(Sudoku::V_SEP_CHAR).count() < 2 {
+            return None;
+        }
+        let first_block = first_row.split(Sudoku::V_SEP_CHAR).nth(1)?;
+        let block_width = Sudoku::parse_entries(first_block, true).ok()?.len();
+        Some((rows.len(), block_width))
+    }
+
+    // whether all the cells of a region can be reached from any one of them by
+    // moving between cells of the region that share a side
+    fn is_region_connected(regions: &[usize], dimension: usize, region: usize) -> bool {
+        let start = match regions.iter().position(|r| *r == region) {
+            Some(start) => start,
+            None => return false
+        };
+        let mut reached = vec![false; regions.len()];
+        reached[start] = true;
+        let mut to_visit = vec![start];
+        let mut num_reached = 1;
+        while let Some(cell) = to_visit.pop() {
+            let (row, col) = (cell / dimension, cell % dimension);
+            let mut neighbours = Vec::new();
+            if row > 0 {
+                neighbours.push(cell - dimension);
+            }
+            if row + 1 < dimension {
+                neighbours.push(cell + dimension);
+            }
+            if col > 0 {
+                neighbours.push(cell - 1);
+            }
+            if col + 1 < dimension {
+                neighbours.push(cell + 1);
+            }
+            for neighbour in neighbours {
+                if !reached[neighbour] && regions[neighbour] == region {
+                    reached[neighbour] = true;
+                    num_reached += 1;
+                    to_visit.push(neighbour);
+                }
+            }
+        }
+        num_reached == regions.iter().filter(|r| **r == region).count()
+    }
+
+    // The shape of the blocks, as (height, width), usually used for a dimension:
+    // square blocks for a perfect square, or otherwise the pair of factors
+    // closest to a square, with the wider side across, e.g. 2x3 for 6x6 and 3x4
+    // for 12x12. Dimensions that are prime have no such shape.
+    pub fn default_block_shape(dimension: usize) -> Option<(usize, usize)> {
+        if dimension == 1 {
+            return Some((1, 1));
+        }
+        (2..=dimension).take_while(|height| height * height <= dimension)
+                       .filter(|height| dimension % height == 0)
+                       .last()
+                       .map(|height| (height, dimension / height))
+    }
+
+    // Split the input into entries, where None represents an empty cell. Besides
+    // whitespace, the only other characters allowed are those used to draw the
+    // grid, and to separate adjacent numbers, along with those marking the cells
+    // on the diagonals if the Sudoku has them.
+    fn parse_entries(str: &str, diagonals: bool) -> Result<Vec<Option<usize>>, SudokuError> {
+        let mut entries: Vec<Option<usize>> = Vec::new();
         let mut val_str = String::new();
+        let mut line = 1;
+        let mut column = 0;
+        let mut token_start = (line, column);
 
         for c in str.chars() {
+            column += 1;
             if c.is_ascii_digit() {
+                if val_str.is_empty() {
+                    token_start = (line, column);
+                }
                 val_str.push(c);
             } else {
-                if val_str.len() > 0 {
-                    if let Ok(val) = val_str.parse::<usize>() {
-                        entries.push(val);
-                        if val > max_val {
-                            max_val = val;
-                        }
-                        val_str = String::new();
-                    }
-                }
+                Sudoku::push_token(&mut entries, &mut val_str, token_start)?;
                 if c == Sudoku::EMPTY_CELL_CHAR {
-                    entries.push(Sudoku::EMPTY_CELL);
+                    entries.push(None);
+                }
+                if c == Sudoku::NEW_LINE_CHAR {
+                    line += 1;
+                    column = 0;
+                } else if !c.is_whitespace() && !Sudoku::is_drawing_char(c, diagonals) {
+                    return Err(SudokuError::UnparsableToken { token: c.to_string(), line, column });
                 }
             }
         }
+        Sudoku::push_token(&mut entries, &mut val_str, token_start)?;
 
-        if val_str.len() > 0 {
-            if let Ok(val) = val_str.parse::<usize>() {
-                entries.push(val);
-                if val > max_val {
-                    max_val = val;
-                }
+        Ok(entries)
+    }
+
+    fn is_drawing_char(c: char, diagonals: bool) -> bool {
+        let is_marker = [Sudoku::LEADING_DIAGONAL_CHAR,
+                         Sudoku::ANTI_DIAGONAL_CHAR,
+                         Sudoku::BOTH_DIAGONALS_CHAR].contains(&c);
+        [Sudoku::EMPTY_CELL_CHAR,
+         Sudoku::H_SEP_CHAR,
+         Sudoku::V_SEP_CHAR,
+         Sudoku::NUMBER_SEP_CHAR].contains(&c) || (diagonals && is_marker)
+    }
+
+    fn push_token(entries: &mut Vec<Option<usize>>,
+                  val_str: &mut String,
+                  (line, column): (usize, usize)) -> Result<(), SudokuError> {
+        if !val_str.is_empty() {
+            match val_str.parse::<usize>() {
+                Ok(val) => entries.push(Some(val)),
+                Err(_) => return Err(SudokuError::UnparsableToken {
+                    token: val_str.clone(),
+                    line,
+                    column
+                })
             }
+            val_str.clear();
         }
+        Ok(())
+    }
 
-        // infer the dimension of this Sudoku
-        let num_entries = entries.len() as f64;
-        let dimension_inferred_from_entries = num_entries.sqrt().ceil() as usize;
-        let dimension = cmp::max(max_val, dimension_inferred_from_entries);
-
-        // create a new Sudoku instance and initialise the cells
-        let mut sudoku = Sudoku::new(dimension);
-        let mut row = 0;
-        let mut col = 0;
-        for val in entries {
-            sudoku.cells.set_element(row, col, val);
-            col += 1;
-            if col >= dimension {
-                col = 0;
-                row += 1;
+    // Take the variant, given by name after "variant:" on a line of its own, out
+    // of the input, leaving the rest of the input with that line blank. Without
+    // it, the Sudoku is a standard one.
+    pub(crate) fn split_variant(str: &str) -> Result<(String, Variant), SudokuError> {
+        let start = match str.find(Sudoku::VARIANT_HEADER) {
+            Some(start) => start,
+            None => return Ok((str.to_string(), Variant::Standard))
+        };
+        let name_start = start + Sudoku::VARIANT_HEADER.len();
+        let end = str[name_start..].find(Sudoku::NEW_LINE_CHAR).map_or(str.len(), |end| name_start + end);
+        let name = str[name_start..end].trim();
+        let variant = name.parse().map_err(|_| {
+            let line_start = str[..start].rfind(Sudoku::NEW_LINE_CHAR).map_or(0, |line_start| line_start + 1);
+            let name_offset = str[name_start..end].find(name).unwrap_or(0);
+            SudokuError::UnparsableToken {
+                token: name.to_string(),
+                line: str[..start].matches(Sudoku::NEW_LINE_CHAR).count() + 1,
+                column: str[line_start..name_start].chars().count() + name_offset + 1
             }
+        })?;
+        Ok((format!("{}{}", &str[..start], &str[end..]), variant))
+    }
+
+    // split the input into the digits and, for a jigsaw Sudoku, the layout of
+    // its regions, which follows "regions:"
+    pub(crate) fn split_regions(str: &str) -> (&str, Option<&str>) {
+        match str.find(Sudoku::REGIONS_HEADER) {
+            Some(start) => (&str[..start], Some(&str[start + Sudoku::REGIONS_HEADER.len()..])),
+            None => (str, None)
         }
+    }
 
-        sudoku
+    // Read the layout of the regions, which labels each cell with a single
+    // character, e.g. a letter, naming its region. Whitespace is ignored, and the
+    // regions are numbered in the order that their labels first appear.
+    pub(crate) fn parse_regions(layout: &str, dimension: usize) -> Result<Vec<usize>, SudokuError> {
+        let mut labels = Vec::new();
+        let regions: Vec<usize> = layout.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match labels.iter().position(|label| *label == c) {
+                Some(region) => region,
+                None => {
+                    labels.push(c);
+                    labels.len() - 1
+                }
+            })
+            .collect();
+        if regions.len() != dimension * dimension {
+            return Err(SudokuError::WrongEntryCount { found: regions.len(), expected: dimension * dimension });
+        }
+        Ok(regions)
+    }
+
+    // the layout of the regions of a jigsaw Sudoku, in the form read by
+    // parse_regions
+    pub(crate) fn regions_string(&self) -> Option<String> {
+        let regions = self.regions.as_ref()?;
+        let labels: Vec<char> = Sudoku::REGION_LABELS.chars().collect();
+        let rows: Vec<String> = regions.chunks(self.dimension)
+            .map(|row| row.iter()
+                          .map(|region| labels[*region].to_string())
+                          .collect::<Vec<String>>()
+                          .join(&Sudoku::SPACE_CHAR.to_string()))
+            .collect();
+        Some(format!("{}\n{}", Sudoku::REGIONS_HEADER, rows.join("\n")))
+    }
+
+    // the line giving the variant of a Sudoku other than a standard one, in the
+    // form read by split_variant
+    pub(crate) fn variant_string(&self) -> Option<String> {
+        match self.variant {
+            Variant::Standard => None,
+            variant => Some(format!("{} {}", Sudoku::VARIANT_HEADER, variant))
+        }
+    }
+
+    // parse a Sudoku whose blocks have the given shape, rather than the default
+    // one for its size; the layout of any regions is ignored
+    pub fn parse_with_block_shape(str: &str, block_height: usize, block_width: usize) -> Result<Self, SudokuError> {
+        let mut sudoku = Sudoku::try_with_block_shape(block_height, block_width)?;
+        let (str, variant) = Sudoku::split_variant(str)?;
+        let (digits, _) = Sudoku::split_regions(&str);
+        let entries = Sudoku::parse_entries(digits, variant != Variant::Standard)?;
+        if entries.len() != sudoku.num_cells() {
+            return Err(SudokuError::WrongEntryCount { found: entries.len(), expected: sudoku.num_cells() });
+        }
+        sudoku.set_variant(variant);
+        sudoku.with_entries(entries)
+    }
+
+    // initialise the cells of an empty Sudoku from the entries parsed for it
+    fn with_entries(mut self, entries: Vec<Option<usize>>) -> Result<Self, SudokuError> {
+        let dimension = self.dimension;
+        for (i, entry) in entries.into_iter().enumerate() {
+            if let Some(val) = entry {
+                let row = i / dimension;
+                let col = i % dimension;
+                if val == 0 || val > dimension {
+                    return Err(SudokuError::ValueOutOfRange { row, col, value: val, max: dimension });
+                }
+                self.cells.set_element(row, col, val);
+            }
+        }
+        Ok(self)
     }
 
     pub fn dimension(&self) -> usize {
         self.dimension
     }
 
-    pub fn block_dimension(&self) -> usize {
-        self.block_dimension
+    // the number of rows in each block (for a jigsaw Sudoku, the block of a
+    // regular Sudoku of its size, or 1 if there is none)
+    pub fn block_height(&self) -> usize {
+        self.block_height
+    }
+
+    // the number of columns in each block (as for block_height)
+    pub fn block_width(&self) -> usize {
+        self.block_width
     }
 
     pub fn num_cells(&self) -> usize {
@@ -118,13 +444,57 @@ impl Sudoku {
         self.cells.set_element(row, col, val);
     }
 
+    pub fn clear_cell(&mut self, row: usize, col: usize) {
+        self.cells.set_element(row, col, Sudoku::EMPTY_CELL);
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    // the number of diagonals that must contain each value once
+    pub(crate) fn num_diagonals(&self) -> usize {
+        match self.variant {
+            Variant::Standard => 0,
+            Variant::X => 2
+        }
+    }
+
+    // the diagonals that must contain each value once passing through a cell,
+    // where 0 is the leading diagonal and 1 the anti-diagonal
+    pub(crate) fn diagonal_nos(&self, row: usize, col: usize) -> Vec<usize> {
+        let mut diagonals = Vec::new();
+        if self.variant == Variant::X {
+            if row == col {
+                diagonals.push(0);
+            }
+            if row + col == self.dimension - 1 {
+                diagonals.push(1);
+            }
+        }
+        diagonals
+    }
+
+    // whether the blocks are irregular regions rather than rectangles
+    pub fn is_jigsaw(&self) -> bool {
+        self.regions.is_some()
+    }
+
+    // the block containing a cell, which is its region in a jigsaw Sudoku
     pub fn block_no(&self, row: usize, col: usize) -> usize {
         debug_assert!(row < self.dimension);
         debug_assert!(col < self.dimension);
 
-        let block_row = row / self.block_dimension;
-        let block_col = col / self.block_dimension;
-        block_col + block_row * self.block_dimension
+        if let Some(regions) = &self.regions {
+            return regions[col + row * self.dimension];
+        }
+        let block_row = row / self.block_height;
+        let block_col = col / self.block_width;
+        block_col + block_row * (self.dimension / self.block_width)
     }
 
     pub fn completed_cells(&self) -> Vec<(usize, usize, usize)> {
@@ -139,6 +509,21 @@ impl Sudoku {
         completed_cells
     }
 
+    // the positions of cells whose contents differ between two Sudokus of the same dimension
+    pub fn differences(&self, other: &Sudoku) -> Vec<(usize, usize)> {
+        debug_assert!(self.dimension == other.dimension);
+
+        let mut differences = Vec::new();
+        for row in 0..self.dimension {
+            for col in 0..self.dimension {
+                if self.cell_value(row, col) != other.cell_value(row, col) {
+                    differences.push((row, col));
+                }
+            }
+        }
+        differences
+    }
+
     pub fn num_completed_cells(&self) -> usize {
         self.completed_cells().len()
     }
@@ -150,13 +535,24 @@ impl Sudoku {
     pub fn is_consistent(&self) -> bool {
         for i in 0..self.dimension {
             // check block i is consistent
-            let col = (i % self.block_dimension) * self.block_dimension;
-            let row = (i / self.block_dimension) * self.block_dimension;
-            let block_iterator = Iterator::new(&self.cells,
-                                               col..col + self.block_dimension,
-                                               row..row + self.block_dimension);
-            if !self.is_region_consistent(block_iterator) {
-                return false;
+            if let Some(regions) = &self.regions {
+                let region_cells: Vec<(usize, usize)> = (0..self.num_cells())
+                    .filter(|cell| regions[*cell] == i)
+                    .map(|cell| (cell % self.dimension, cell / self.dimension))
+                    .collect();
+                if !self.is_region_consistent(region_cells) {
+                    return false;
+                }
+            } else {
+                let blocks_across = self.dimension / self.block_width;
+                let col = (i % blocks_across) * self.block_width;
+                let row = (i / blocks_across) * self.block_height;
+                let block_iterator = Iterator::new(&self.cells,
+                                                   col..col + self.block_width,
+                                                   row..row + self.block_height);
+                if !self.is_region_consistent(block_iterator) {
+                    return false;
+                }
             }
 
             // check row i is consistent
@@ -171,14 +567,36 @@ impl Sudoku {
                 return false;
             }
         }
+
+        // check the diagonals are consistent
+        for diagonal in 0..self.num_diagonals() {
+            let diagonal_cells: Vec<(usize, usize)> = (0..self.dimension)
+                .map(|row| if diagonal == 0 { (row, row) } else { (self.dimension - 1 - row, row) })
+                .collect();
+            if !self.is_region_consistent(diagonal_cells) {
+                return false;
+            }
+        }
         true
     }
 
-    fn is_region_consistent(&self, iterator: Iterator) -> bool {
+    // the character before a cell in the grid, which marks the cells on the
+    // diagonals of a Sudoku-X
+    fn cell_marker(&self, row: usize, col: usize) -> char {
+        match self.diagonal_nos(row, col).as_slice() {
+            [0, 1] => Sudoku::BOTH_DIAGONALS_CHAR,
+            [0] => Sudoku::LEADING_DIAGONAL_CHAR,
+            [1] => Sudoku::ANTI_DIAGONAL_CHAR,
+            _ => Sudoku::SPACE_CHAR
+        }
+    }
+
+    // whether no value is repeated among some cells, given as (col, row)
+    fn is_region_consistent<I: IntoIterator<Item = (usize, usize)>>(&self, cells: I) -> bool {
         let mut completed = vec![false; self.dimension];
 
-        for cell in iterator {
-            let (row, col) = cell;
+        for cell in cells {
+            let (col, row) = cell;
             if let Some(val) = self.cell_value(row, col) {
                 if completed[val - 1] {
                     return false;
@@ -191,22 +609,75 @@ impl Sudoku {
         true
     }
 
-    pub fn to_string(&self) -> String {
+    // The grid of a jigsaw Sudoku, with lines drawn between cells in different
+    // regions. Each cell is shown between its marker and a space, and a vertical
+    // line or a space follows it; a rule goes under each cell in a different
+    // region from the one below it.
+    fn jigsaw_grid_string(&self, regions: &[usize]) -> String {
         let chars_per_cell = self.dimension.to_string().len();
-        let chars_wide = ((chars_per_cell + 1) * self.dimension) + (self.block_dimension * 2) + 1;
+        let dimension = self.dimension;
+        let region = |row: usize, col: usize| regions[col + row * dimension];
+        let is_v_boundary = |row: usize, col: usize| col + 1 == dimension || region(row, col) != region(row, col + 1);
+        let cell_rule = |ch: char| ch.to_string().repeat(chars_per_cell + 2);
+        let horiz_rule = Sudoku::H_SEP_CHAR.to_string().repeat(1 + dimension * (chars_per_cell + 3));
+        let mut lines = vec![horiz_rule.clone()];
+
+        for row in 0..dimension {
+            // add the rules between this row and the one above
+            if row > 0 {
+                let is_h_boundary = |col: usize| region(row - 1, col) != region(row, col);
+                let mut line = Sudoku::V_SEP_CHAR.to_string();
+                for col in 0..dimension {
+                    line.push_str(&cell_rule(if is_h_boundary(col) { Sudoku::H_SEP_CHAR } else { Sudoku::SPACE_CHAR }));
+                    line.push(if col + 1 == dimension {
+                        Sudoku::V_SEP_CHAR
+                    } else if is_h_boundary(col) || is_h_boundary(col + 1) {
+                        Sudoku::H_SEP_CHAR
+                    } else if is_v_boundary(row - 1, col) || is_v_boundary(row, col) {
+                        Sudoku::V_SEP_CHAR
+                    } else {
+                        Sudoku::SPACE_CHAR
+                    });
+                }
+                lines.push(line);
+            }
+
+            let mut line = Sudoku::V_SEP_CHAR.to_string();
+            for col in 0..dimension {
+                let cell_str = match self.cell_value(row, col) {
+                        Some(val) => val.to_string(),
+                        None => Sudoku::EMPTY_CELL_CHAR.to_string()
+                };
+                line = format!("{}{}{:>w$} ", line, self.cell_marker(row, col), cell_str, w=chars_per_cell);
+                line.push(if is_v_boundary(row, col) { Sudoku::V_SEP_CHAR } else { Sudoku::SPACE_CHAR });
+            }
+            lines.push(line);
+        }
+
+        lines.push(horiz_rule);
+        lines.join(&Sudoku::NEW_LINE_CHAR.to_string())
+    }
+
+    fn grid_string(&self) -> String {
+        if let Some(regions) = &self.regions {
+            return self.jigsaw_grid_string(regions);
+        }
+        let chars_per_cell = self.dimension.to_string().len();
+        let blocks_across = self.dimension / self.block_width;
+        let chars_wide = ((chars_per_cell + 1) * self.dimension) + (blocks_across * 2) + 1;
         let horiz_rule = &Sudoku::H_SEP_CHAR.to_string().repeat(chars_wide);
         let mut sud_str = String::new();
 
         for row in 0..self.dimension {
             // add the horizontal lines
-            if row % self.block_dimension == 0 {
+            if row % self.block_height == 0 {
                 sud_str.push_str(horiz_rule);
                 sud_str.push(Sudoku::NEW_LINE_CHAR);
             }
 
             for col in 0..self.dimension {
                 // check if a block separator is needed
-                if col % self.block_dimension == 0 {
+                if col % self.block_width == 0 {
                     if col > 0 {
                         sud_str.push(Sudoku::SPACE_CHAR);
                     }
@@ -214,7 +685,7 @@ impl Sudoku {
                 }
 
                 // add the contents of each cell
-                sud_str.push(Sudoku::SPACE_CHAR);
+                sud_str.push(self.cell_marker(row, col));
 
                 let cell_str = match self.cell_value(row, col) {
                         Some(val) => val.to_string(),
@@ -235,14 +706,195 @@ impl Sudoku {
     }
 }
 
+impl FromStr for Sudoku {
+    type Err = SudokuError;
+
+    // The shape of the blocks is the one drawn in the grid, as by Display, or
+    // else the default one for the number of entries, unless the layout of the
+    // regions of a jigsaw Sudoku follows the digits. The variant may be given on
+    // a line of its own, and the cells on the diagonals of a Sudoku-X may be
+    // marked as by Display only if it is.
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let (str, variant) = Sudoku::split_variant(str)?;
+        let (digits, layout) = Sudoku::split_regions(&str);
+        let entries = Sudoku::parse_entries(digits, variant != Variant::Standard)?;
+
+        // refuse to go any further with inputs that are too large
+        let max_entries = Sudoku::MAX_DIMENSION.pow(2);
+        if entries.len() > max_entries {
+            return Err(SudokuError::TooManyEntries { found: entries.len(), max: max_entries });
+        }
+
+        // infer the dimension of this Sudoku
+        let dimension = (entries.len() as f64).sqrt() as usize;
+        if dimension.pow(2) != entries.len() {
+            return Err(SudokuError::NonSquareEntryCount(entries.len()));
+        }
+
+        let mut sudoku = match layout {
+            Some(layout) => Sudoku::try_with_regions(&Sudoku::parse_regions(layout, dimension)?)?,
+            None => match Sudoku::drawn_block_shape(digits) {
+                Some((block_height, block_width)) if block_height * block_width == dimension => {
+                    Sudoku::try_with_block_shape(block_height, block_width)?
+                }
+                _ => Sudoku::try_new(dimension)?
+            }
+        };
+        sudoku.set_variant(variant);
+        sudoku.with_entries(entries)
+    }
+}
+
 impl fmt::Display for Sudoku {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_string())
+        write!(f, "{}", self.grid_string())?;
+        if let Some(regions_str) = self.regions_string() {
+            write!(f, "{}{}", Sudoku::NEW_LINE_CHAR, regions_str)?;
+        }
+        if let Some(variant_str) = self.variant_string() {
+            write!(f, "{}{}", Sudoku::NEW_LINE_CHAR, variant_str)?;
+        }
+        Ok(())
     }
 }
 
+// parse a 9x9 Sudoku given as a single line of 81 characters, for tests
+#[cfg(test)]
+pub(crate) fn parse_line(line: &str) -> Sudoku {
+    line.chars().map(|c| c.to_string()).collect::<Vec<_>>().join(" ").parse().unwrap()
+}
+
+#[cfg(test)]
 mod tests {
-    use super::Sudoku;
+    use super::{ParseVariantError, Sudoku, Variant};
+    use super::SudokuError;
+
+    #[test]
+    fn test_try_new_invalid_dimension() {
+        assert_eq!(Err(SudokuError::InvalidDimension(0)), Sudoku::try_new(0).map(|_| ()));
+        assert_eq!(Err(SudokuError::InvalidDimension(5)), Sudoku::try_new(5).map(|_| ()));
+        assert!(Sudoku::try_new(9).is_ok());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let sudoku: Sudoku = "3.4. ..2. 1... .4.3".parse().unwrap();
+        assert_eq!(4, sudoku.dimension());
+        assert_eq!(Some(3), sudoku.cell_value(0, 0));
+        assert_eq!(None, sudoku.cell_value(0, 1));
+        assert_eq!(Some(2), sudoku.cell_value(1, 2));
+        assert_eq!(Some(3), sudoku.cell_value(3, 3));
+    }
+
+    #[test]
+    fn test_from_str_errors() {
+        assert_eq!(Err(SudokuError::NonSquareEntryCount(5)),
+                   "1.2.3".parse::<Sudoku>().map(|_| ()));
+        assert_eq!(Err(SudokuError::InvalidDimension(3)),
+                   "1.2.3.2.1".parse::<Sudoku>().map(|_| ()));
+        assert_eq!(Err(SudokuError::ValueOutOfRange { row: 1, col: 1, value: 5, max: 4 }),
+                   "....|.5..|....|....".parse::<Sudoku>().map(|_| ()));
+        assert_eq!(Err(SudokuError::ValueOutOfRange { row: 0, col: 0, value: 0, max: 4 }),
+                   "0...|....|....|....".parse::<Sudoku>().map(|_| ()));
+        assert_eq!(Err(SudokuError::UnparsableToken {
+                       token: "99999999999999999999999".to_string(), line: 2, column: 3
+                   }),
+                   "....\n| 99999999999999999999999".parse::<Sudoku>().map(|_| ()));
+        assert_eq!(Err(SudokuError::TooManyEntries { found: 4097, max: 4096 }),
+                   ".".repeat(4097).parse::<Sudoku>().map(|_| ()));
+        assert_eq!(Err(SudokuError::UnparsableToken { token: "x".to_string(), line: 1, column: 2 }),
+                   "3x.4. .1.2 .4.3 2.1.".parse::<Sudoku>().map(|_| ()));
+        assert_eq!(Err(SudokuError::UnparsableToken { token: "?".to_string(), line: 2, column: 4 }),
+                   "3.4.\n.1.?2\n.4.3\n2.1.".parse::<Sudoku>().map(|_| ()));
+
+        // the cells on the diagonals may only be marked if the variant has them
+        assert_eq!(Err(SudokuError::UnparsableToken { token: "X".to_string(), line: 1, column: 2 }),
+                   "1X2.. .... .... ....".parse::<Sudoku>().map(|_| ()));
+        assert_eq!(Err(SudokuError::UnparsableToken { token: "\\".to_string(), line: 1, column: 1 }),
+                   "\\1... .... .... ....".parse::<Sudoku>().map(|_| ()));
+        assert!("X1... ./..\\. .X... ....\nvariant: x".parse::<Sudoku>().is_ok());
+
+        // numbers may be separated by '~'
+        assert!("1~2~3~4 .... .... ....".parse::<Sudoku>().is_ok());
+    }
+
+    #[test]
+    fn test_block_shape() {
+        assert_eq!(Some((1, 1)), Sudoku::default_block_shape(1));
+        assert_eq!(Some((2, 3)), Sudoku::default_block_shape(6));
+        assert_eq!(Some((2, 4)), Sudoku::default_block_shape(8));
+        assert_eq!(Some((3, 3)), Sudoku::default_block_shape(9));
+        assert_eq!(Some((2, 5)), Sudoku::default_block_shape(10));
+        assert_eq!(Some((3, 4)), Sudoku::default_block_shape(12));
+        assert_eq!(None, Sudoku::default_block_shape(7));
+
+        let sudoku = Sudoku::new(6);
+        assert_eq!((2, 3), (sudoku.block_height(), sudoku.block_width()));
+        assert_eq!(0, sudoku.block_no(1, 2));
+        assert_eq!(1, sudoku.block_no(0, 3));
+        assert_eq!(2, sudoku.block_no(2, 0));
+        assert_eq!(5, sudoku.block_no(5, 5));
+
+        let sudoku = Sudoku::with_block_shape(3, 2);
+        assert_eq!(6, sudoku.dimension());
+        assert_eq!(1, sudoku.block_no(0, 2));
+        assert_eq!(4, sudoku.block_no(3, 2));
+        assert_eq!(Err(SudokuError::InvalidBlockShape { height: 0, width: 3 }),
+                   Sudoku::try_with_block_shape(0, 3).map(|_| ()));
+        assert_eq!(Err(SudokuError::InvalidBlockShape { height: 9, width: 8 }),
+                   Sudoku::try_with_block_shape(9, 8).map(|_| ()));
+    }
+
+    #[test]
+    fn test_parse_with_block_shape() {
+        let rows = "1 2 3 4 5 6 ...... ...... ...... ...... ......";
+        let sudoku: Sudoku = rows.parse().unwrap();
+        assert_eq!((2, 3), (sudoku.block_height(), sudoku.block_width()));
+        let sudoku = Sudoku::parse_with_block_shape(rows, 3, 2).unwrap();
+        assert_eq!((3, 2), (sudoku.block_height(), sudoku.block_width()));
+        assert_eq!(Some(6), sudoku.cell_value(0, 5));
+        assert_eq!(Err(SudokuError::WrongEntryCount { found: 36, expected: 16 }),
+                   Sudoku::parse_with_block_shape(rows, 2, 2).map(|_| ()));
+    }
+
+    #[test]
+    fn test_to_string_rectangular() {
+        let sudoku: Sudoku = "1..... ...... ...... ...... ...... .....6".parse().unwrap();
+        let expected = "-----------------\n\
+                        | 1 . . | . . . |\n\
+                        | . . . | . . . |\n\
+                        -----------------\n\
+                        | . . . | . . . |\n\
+                        | . . . | . . . |\n\
+                        -----------------\n\
+                        | . . . | . . . |\n\
+                        | . . . | . . 6 |\n\
+                        -----------------";
+        assert_eq!(expected, sudoku.to_string());
+        assert_eq!(Ok(sudoku.clone()), sudoku.to_string().parse());
+
+        // the shape of the blocks is read back from the grid
+        let mut sudoku = Sudoku::with_block_shape(3, 2);
+        sudoku.set_cell_value(0, 0, 1);
+        sudoku.set_cell_value(5, 5, 6);
+        let parsed: Sudoku = sudoku.to_string().parse().unwrap();
+        assert_eq!((3, 2), (parsed.block_height(), parsed.block_width()));
+        assert_eq!(sudoku, parsed);
+
+        // but a grid that is not drawn has the default shape
+        let parsed: Sudoku = "1..... ...... ...... ...... ...... .....6".parse().unwrap();
+        assert_eq!((2, 3), (parsed.block_height(), parsed.block_width()));
+    }
+
+    #[test]
+    fn test_clear_cell() {
+        let mut sudoku: Sudoku = "3.4. ..2. 1... .4.3".parse().unwrap();
+        sudoku.clear_cell(0, 0);
+        sudoku.clear_cell(0, 1);
+        assert_eq!(None, sudoku.cell_value(0, 0));
+        assert_eq!(None, sudoku.cell_value(0, 1));
+        assert_eq!(5, sudoku.num_completed_cells());
+    }
 
     #[test]
     fn test_is_consistent_row() {
@@ -297,4 +949,153 @@ mod tests {
         sudoku.set_cell_value(1, 1, 1);
         assert!(!sudoku.is_consistent());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_is_consistent_rectangular_block() {
+        // the cells (0, 2) and (1, 0) share a 2x3 block, but not a 3x2 one
+        let mut sudoku = Sudoku::new(6);
+        sudoku.set_cell_value(0, 2, 1);
+        sudoku.set_cell_value(1, 0, 1);
+        assert!(!sudoku.is_consistent());
+
+        let mut sudoku = Sudoku::with_block_shape(3, 2);
+        sudoku.set_cell_value(0, 2, 1);
+        sudoku.set_cell_value(1, 0, 1);
+        assert!(sudoku.is_consistent());
+        sudoku.set_cell_value(2, 1, 1);
+        assert!(!sudoku.is_consistent());
+    }
+
+    #[test]
+    fn test_try_with_regions() {
+        let sudoku = Sudoku::with_regions(&[0, 0, 0, 1, 2, 0, 1, 1, 2, 2, 3, 1, 2, 3, 3, 3]);
+        assert!(sudoku.is_jigsaw());
+        assert!(!Sudoku::new(4).is_jigsaw());
+        assert_eq!(4, sudoku.dimension());
+        assert_eq!(0, sudoku.block_no(1, 1));
+        assert_eq!(1, sudoku.block_no(1, 2));
+        assert_eq!(2, sudoku.block_no(3, 0));
+        assert_eq!(3, sudoku.block_no(2, 2));
+
+        // regions may also be used for Sudokus with a prime dimension
+        let sudoku = Sudoku::with_regions(&[0, 0, 0, 1, 1,
+                                            0, 0, 1, 1, 1,
+                                            2, 2, 2, 3, 3,
+                                            2, 2, 3, 3, 3,
+                                            4, 4, 4, 4, 4]);
+        assert_eq!(5, sudoku.dimension());
+        assert_eq!(3, sudoku.block_no(3, 2));
+    }
+
+    #[test]
+    fn test_try_with_regions_errors() {
+        assert_eq!(Err(SudokuError::NonSquareEntryCount(3)),
+                   Sudoku::try_with_regions(&[0, 0, 0]).map(|_| ()));
+        assert_eq!(Err(SudokuError::WrongRegionSize { region: 0, size: 5, expected: 4 }),
+                   Sudoku::try_with_regions(&[0, 0, 0, 0, 0, 1, 1, 1, 2, 2, 3, 3, 2, 2, 3, 3]).map(|_| ()));
+        assert_eq!(Err(SudokuError::WrongRegionSize { region: 3, size: 3, expected: 4 }),
+                   Sudoku::try_with_regions(&[0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 3, 3, 2, 2, 3, 4]).map(|_| ()));
+        assert_eq!(Err(SudokuError::DisconnectedRegion(0)),
+                   Sudoku::try_with_regions(&[0, 0, 1, 1, 1, 1, 0, 0, 2, 2, 3, 3, 2, 2, 3, 3]).map(|_| ()));
+    }
+
+    #[test]
+    fn test_from_str_jigsaw() {
+        let sudoku: Sudoku = "1... .... .... ...4\nregions:\nAAAB\nCABB\nCCDB\nCDDD".parse().unwrap();
+        assert!(sudoku.is_jigsaw());
+        assert_eq!(Some(1), sudoku.cell_value(0, 0));
+        assert_eq!(Some(4), sudoku.cell_value(3, 3));
+        assert_eq!(1, sudoku.block_no(2, 3));
+
+        let expected = "-----------------\n\
+                        | 1   .   . | . |\n\
+                        |----   -----   |\n\
+                        | . | . | .   . |\n\
+                        |   ---------   |\n\
+                        | .   . | . | . |\n\
+                        |   -----   ----|\n\
+                        | . | .   .   4 |\n\
+                        -----------------\n\
+                        regions:\n\
+                        A A A B\n\
+                        C A B B\n\
+                        C C D B\n\
+                        C D D D";
+        assert_eq!(expected, sudoku.to_string());
+        assert_eq!(Ok(sudoku.clone()), sudoku.to_string().parse());
+
+        assert_eq!(Err(SudokuError::WrongEntryCount { found: 15, expected: 16 }),
+                   "................ regions: AAAB CABB CCDB CDD".parse::<Sudoku>().map(|_| ()));
+        assert_eq!(Err(SudokuError::DisconnectedRegion(0)),
+                   "................ regions: AABB BBAA CCDD CCDD".parse::<Sudoku>().map(|_| ()));
+
+        // a given block shape takes the place of the regions
+        let sudoku = Sudoku::parse_with_block_shape("................ regions: AAAB CABB CCDB CDDD", 2, 2).unwrap();
+        assert!(!sudoku.is_jigsaw());
+    }
+
+    #[test]
+    fn test_is_consistent_jigsaw() {
+        // the cells (1, 1) and (0, 2) share a region, but not a block
+        let mut sudoku = Sudoku::with_regions(&[0, 0, 0, 1, 2, 0, 1, 1, 2, 2, 3, 1, 2, 3, 3, 3]);
+        sudoku.set_cell_value(1, 1, 1);
+        sudoku.set_cell_value(2, 3, 1);
+        assert!(sudoku.is_consistent());
+        sudoku.set_cell_value(0, 2, 1);
+        assert!(!sudoku.is_consistent());
+    }
+
+    #[test]
+    fn test_parse_variant() {
+        assert_eq!(Ok(Variant::Standard), "standard".parse());
+        assert_eq!(Ok(Variant::X), "x".parse());
+        assert_eq!(Err(ParseVariantError("y".to_string())), "y".parse::<Variant>());
+    }
+
+    #[test]
+    fn test_is_consistent_diagonal() {
+        // the cells (0, 0) and (3, 3) share the leading diagonal, and (1, 2) and
+        // (2, 1) the anti-diagonal
+        let mut sudoku = Sudoku::new(4);
+        sudoku.set_cell_value(0, 0, 1);
+        sudoku.set_cell_value(3, 3, 1);
+        sudoku.set_cell_value(1, 2, 2);
+        sudoku.set_cell_value(2, 1, 2);
+        assert!(sudoku.is_consistent());
+        sudoku.set_variant(Variant::X);
+        assert!(!sudoku.is_consistent());
+        sudoku.clear_cell(3, 3);
+        assert!(!sudoku.is_consistent());
+        sudoku.clear_cell(2, 1);
+        assert!(sudoku.is_consistent());
+    }
+
+    #[test]
+    fn test_to_string_diagonal() {
+        let mut sudoku: Sudoku = "1... .... .... ...4".parse().unwrap();
+        sudoku.set_variant(Variant::X);
+        let expected = "-------------\n\
+                        |\\1 . | ./. |\n\
+                        | .\\. |/. . |\n\
+                        -------------\n\
+                        | ./. |\\. . |\n\
+                        |/. . | .\\4 |\n\
+                        -------------\n\
+                        variant: x";
+        assert_eq!(expected, sudoku.to_string());
+        assert_eq!(Ok(sudoku.clone()), sudoku.to_string().parse());
+        assert_eq!(Variant::X, "variant: x\n1... .... .... ...4".parse::<Sudoku>().unwrap().variant());
+        assert_eq!(Err(SudokuError::UnparsableToken { token: "y".to_string(), line: 2, column: 11 }),
+                   "1... .... .... ...4\nvariant:  y".parse::<Sudoku>().map(|_| ()));
+
+        // the cell in the middle of an odd-sized grid is on both diagonals
+        let mut sudoku = Sudoku::with_regions(&[0, 0, 0, 1, 1,
+                                                0, 0, 1, 1, 1,
+                                                2, 2, 2, 3, 3,
+                                                2, 2, 3, 3, 3,
+                                                4, 4, 4, 4, 4]);
+        sudoku.set_variant(Variant::X);
+        assert_eq!(Some("| .   .  X. | .   . |"), sudoku.to_string().lines().nth(5));
+        assert_eq!(Some("|/.   .   .   .  \\. |"), sudoku.to_string().lines().nth(9));
+    }
+}